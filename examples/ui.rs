@@ -1,9 +1,11 @@
-use tui_renderer::{buffer::{Buffer, Rect}, widget::{layout::{Vertical, Constraint, Alignment, Horizontal}, widget::Widget}, effect::Color};
+use std::io::stdout;
+
+use tui_renderer::{app::Window, backend::termion::TermionBackend, buffer::{Buffer, BufferView}, widget::{layout::{Vertical, Constraint, Alignment, Horizontal}, widget::Widget}, effect::Color};
 
 fn main() {
     let (width, height) = termion::terminal_size().unwrap();
     let mut buf = Buffer::new(width as usize, height as usize);
-    let rect = Rect::new(0, 0, width as usize, height as usize);
+    let mut window = Window::new(TermionBackend::new(stdout()));
 
     let mut ui = Vertical::new(Constraint::percentage(100, 1000, 0), Alignment::Start)
                         .add(Box::from(
@@ -30,9 +32,8 @@ fn main() {
                             .set_color(Some(Color::from_rgb(255, 255, 255)), Some(Color::from_rgb(70, 90, 255)))
                         ));
     
-    ui.write_buffer(&mut buf, rect);
+    ui.write_buffer(&mut BufferView::new(&mut buf));
 
-    for l in buf.get_buffer() {
-        println!("{}", l.as_str());
-    }
-}
\ No newline at end of file
+    window.render(&mut buf).unwrap();
+    println!();
+}
@@ -0,0 +1,431 @@
+use crate::buffer::{Buffer, Cell, symbols};
+use crate::effect::{Color, Effect, Modifier, Style};
+
+// cursor movements in parsed text never go further right than this
+const MAX_COLUMN: usize = 1024;
+
+/// Splits text with escape codes, like the output of `ls --color`, into
+/// lines plus the effects which reproduce its colors, ready to be passed to
+/// `Buffer::push_rect_buffer`. Cursor movements are applied within the
+/// text: they never leave it above, below or to the left.
+pub fn parse(text: &str) -> (Vec<String>, Vec<Vec<Effect>>) {
+    let mut page = Page::new();
+    for token in tokenize(text).0 {
+        match token {
+            Token::Text(text) => page.print(&text),
+            Token::Csi(params, action) => page.csi(&params, action),
+            Token::Link(url) => page.style.apply(&Effect::link(&url, 0)),
+        }
+    }
+    page.into_lines()
+}
+
+/// Applies terminal output to a `Buffer` the way a terminal would, so that
+/// escape coded text can be turned back into cells.
+pub struct Screen {
+    buffer: Buffer,
+    cursor: (usize, usize),
+    style: Style,
+    // an escape sequence split between two `feed` calls
+    pending: String,
+}
+
+impl Screen {
+    pub fn new(width: usize, height: usize) -> Self {
+        Screen { buffer: Buffer::new(width, height), cursor: (0, 0), style: Style::default(), pending: String::new() }
+    }
+
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    pub fn into_buffer(self) -> Buffer {
+        self.buffer
+    }
+
+    pub fn cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    pub fn feed(&mut self, output: &str) {
+        let input = std::mem::take(&mut self.pending) + output;
+        let (tokens, pending) = tokenize(&input);
+        for token in tokens {
+            match token {
+                Token::Text(text) => self.print(&text),
+                Token::Csi(params, action) => self.csi(&params, action),
+                Token::Link(url) => self.style.apply(&Effect::link(&url, 0)),
+            }
+        }
+        self.pending = pending;
+    }
+
+    fn print(&mut self, text: &str) {
+        let width = self.buffer.width();
+        let height = self.buffer.height();
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        for part in split_controls(text) {
+            let (x, y) = self.cursor;
+            match part {
+                "\n" => self.cursor.1 = (y + 1).min(height - 1),
+                "\r" => self.cursor.0 = 0,
+                "\x08" => self.cursor.0 = x.saturating_sub(1),
+                "\t" => self.cursor.0 = next_tab(x).min(width - 1),
+                _ => {
+                    for (symbol, s_width) in symbols(part) {
+                        if self.cursor.0 + s_width > width {
+                            self.cursor = (0, (self.cursor.1 + 1).min(height - 1));
+                        }
+                        let (x, y) = self.cursor;
+                        self.buffer.set_symbol(x, y, &symbol, &self.style);
+                        self.cursor.0 += s_width;
+                    }
+                }
+            }
+        }
+    }
+
+    fn csi(&mut self, params: &str, action: char) {
+        let args = args(params);
+        let arg = |idx: usize, default: usize| arg(&args, idx, default);
+        let last_x = self.buffer.width().saturating_sub(1);
+        let last_y = self.buffer.height().saturating_sub(1);
+        let (x, y) = self.cursor;
+
+        match action {
+            'A' => self.cursor.1 = y.saturating_sub(arg(0, 1)),
+            'B' => self.cursor.1 = y.saturating_add(arg(0, 1)).min(last_y),
+            'C' => self.cursor.0 = x.saturating_add(arg(0, 1)).min(last_x),
+            'D' => self.cursor.0 = x.saturating_sub(arg(0, 1)),
+            'E' => self.cursor = (0, y.saturating_add(arg(0, 1)).min(last_y)),
+            'F' => self.cursor = (0, y.saturating_sub(arg(0, 1))),
+            'G' => self.cursor.0 = (arg(0, 1) - 1).min(last_x),
+            'H' | 'f' => self.cursor = ((arg(1, 1) - 1).min(last_x), (arg(0, 1) - 1).min(last_y)),
+            'J' => {
+                let (width, height) = (self.buffer.width(), self.buffer.height());
+                match arg(0, 0) {
+                    0 => {
+                        self.erase(y, x, width);
+                        (y + 1..height).for_each(|row| self.erase(row, 0, width));
+                    }
+                    1 => {
+                        (0..y).for_each(|row| self.erase(row, 0, width));
+                        self.erase(y, 0, x + 1);
+                    }
+                    _ => self.buffer = Buffer::new(width, height),
+                }
+            }
+            'K' => match arg(0, 0) {
+                0 => self.erase(y, x, self.buffer.width()),
+                1 => self.erase(y, 0, x + 1),
+                _ => self.erase(y, 0, self.buffer.width()),
+            },
+            'm' => apply_sgr(&mut self.style, &args),
+            _ => {}
+        }
+    }
+
+    // Blanks the columns `from..to` of a row.
+    fn erase(&mut self, y: usize, from: usize, to: usize) {
+        let style = Style::default();
+        for x in from..to.min(self.buffer.width()) {
+            self.buffer.set_symbol(x, y, " ", &style);
+        }
+    }
+}
+
+// Growable grid for `parse`. Unlike on a screen a line feed also returns the
+// cursor, as text from a pipe has no terminal translating it.
+struct Page {
+    lines: Vec<Vec<Cell>>,
+    cursor: (usize, usize),
+    style: Style,
+}
+
+impl Page {
+    fn new() -> Self {
+        Page { lines: vec![vec![]], cursor: (0, 0), style: Style::default() }
+    }
+
+    fn print(&mut self, text: &str) {
+        for part in split_controls(text) {
+            let (x, y) = self.cursor;
+            match part {
+                "\n" => {
+                    self.cursor = (0, y + 1);
+                    if self.lines.len() == y + 1 {
+                        self.lines.push(vec![]);
+                    }
+                }
+                "\r" => self.cursor.0 = 0,
+                "\x08" => self.cursor.0 = x.saturating_sub(1),
+                "\t" => self.cursor.0 = next_tab(x).min(MAX_COLUMN),
+                _ => {
+                    for (symbol, s_width) in symbols(part) {
+                        let (x, y) = self.cursor;
+                        self.put(x, y, Cell::new(&symbol, s_width, self.style.clone()));
+                        self.cursor.0 += s_width;
+                    }
+                }
+            }
+        }
+    }
+
+    // Places a glyph, blanking the parts of wide glyphs it overwrites.
+    fn put(&mut self, x: usize, y: usize, cell: Cell) {
+        let width = cell.width;
+        let line = &mut self.lines[y];
+        if line.len() < x + width {
+            line.resize(x + width, Cell::default());
+        }
+        if line[x].width == 0 && x > 0 {
+            line[x - 1] = Cell::blank(line[x - 1].style.clone());
+        }
+        if line.get(x + width).is_some_and(|next| next.width == 0) {
+            line[x + width] = Cell::blank(line[x + width].style.clone());
+        }
+        for i in 1..width {
+            line[x + i] = Cell::new("", 0, cell.style.clone());
+        }
+        line[x] = cell;
+    }
+
+    fn csi(&mut self, params: &str, action: char) {
+        let args = args(params);
+        let arg = |idx: usize, default: usize| arg(&args, idx, default);
+        let last_y = self.lines.len() - 1;
+        let (x, y) = self.cursor;
+
+        match action {
+            'A' => self.cursor.1 = y.saturating_sub(arg(0, 1)),
+            'B' => self.cursor.1 = y.saturating_add(arg(0, 1)).min(last_y),
+            'C' => self.cursor.0 = x.saturating_add(arg(0, 1)).min(MAX_COLUMN),
+            'D' => self.cursor.0 = x.saturating_sub(arg(0, 1)),
+            'E' => self.cursor = (0, y.saturating_add(arg(0, 1)).min(last_y)),
+            'F' => self.cursor = (0, y.saturating_sub(arg(0, 1))),
+            'G' => self.cursor.0 = (arg(0, 1) - 1).min(MAX_COLUMN),
+            'H' | 'f' => self.cursor = ((arg(1, 1) - 1).min(MAX_COLUMN), (arg(0, 1) - 1).min(last_y)),
+            'K' => {
+                let line = &mut self.lines[y];
+                match arg(0, 0) {
+                    0 => line.truncate(x),
+                    1 => line.iter_mut().take(x + 1).for_each(|cell| *cell = Cell::default()),
+                    _ => line.clear(),
+                }
+            }
+            'm' => apply_sgr(&mut self.style, &args),
+            _ => {}
+        }
+    }
+
+    fn into_lines(mut self) -> (Vec<String>, Vec<Vec<Effect>>) {
+        // output usually ends with a line feed, which opens no new line
+        if self.lines.len() > 1 && self.lines.last().is_some_and(|line| line.is_empty()) {
+            self.lines.pop();
+        }
+
+        let mut texts = vec![];
+        let mut effects = vec![];
+        for line in self.lines {
+            let mut text = String::new();
+            let mut line_effs = vec![];
+            let mut style = Style::default();
+            for (x, cell) in line.iter().enumerate().filter(|(_, cell)| cell.width > 0) {
+                if cell.style != style {
+                    style = cell.style.clone();
+                    line_effs.extend(style.to_effects(x));
+                }
+                text += &cell.symbol;
+            }
+            // padding after the text must not take the last style
+            if !style.is_default() {
+                line_effs.push(Effect::reset(line.len()));
+            }
+            texts.push(text);
+            effects.push(line_effs);
+        }
+        (texts, effects)
+    }
+}
+
+enum Token {
+    Text(String),
+    Csi(String, char),
+    // an empty url ends the link
+    Link(String),
+}
+
+// Splits terminal output into text, CSI sequences and links, dropping the
+// escape sequences which don't draw anything. Also returns the start of a sequence
+// cut off at the end of the input.
+fn tokenize(input: &str) -> (Vec<Token>, String) {
+    let mut tokens = vec![];
+    let mut text = String::new();
+    let mut chars = input.char_indices().peekable();
+    // an escape which cut a command short and starts the next sequence
+    let mut resume = None;
+
+    while let Some((start, ch)) = resume.take().or_else(|| chars.next()) {
+        if ch != '\x1b' {
+            text.push(ch);
+            continue;
+        }
+        if !text.is_empty() {
+            tokens.push(Token::Text(std::mem::take(&mut text)));
+        }
+
+        let complete = match chars.next().map(|(_, c)| c) {
+            Some('[') => {
+                let mut params = String::new();
+                let mut done = false;
+                for (_, c) in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        tokens.push(Token::Csi(params, c));
+                        done = true;
+                        break;
+                    }
+                    params.push(c);
+                }
+                done
+            }
+            // operating system commands end with BEL or ESC \, any other
+            // escape cancels them
+            Some(']') => {
+                let mut command = String::new();
+                let mut done = false;
+                while let Some((i, c)) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if(|(_, c)| *c == '\\').is_some()) {
+                        done = true;
+                        break;
+                    }
+                    if c == '\x1b' {
+                        if chars.peek().is_some() {
+                            resume = Some((i, c));
+                        }
+                        break;
+                    }
+                    command.push(c);
+                }
+                // OSC 8 ; params ; url
+                if let Some((_, url)) = command.strip_prefix("8;").and_then(|link| link.split_once(';')) {
+                    if done {
+                        tokens.push(Token::Link(url.to_string()));
+                    }
+                }
+                done || resume.is_some()
+            }
+            // character set selection carries one more byte
+            Some('(' | ')' | '*' | '+') => chars.next().is_some(),
+            Some(_) => true,
+            None => false,
+        };
+        if !complete {
+            return (tokens, input[start..].to_string());
+        }
+    }
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+    (tokens, String::new())
+}
+
+// Numeric CSI parameters. Empty ones are 0 and ones which don't parse, such
+// as the ':' separated forms, are out of range so that they are ignored.
+fn args(params: &str) -> Vec<usize> {
+    params.split(';')
+        .map(|p| if p.is_empty() { 0 } else { p.parse().unwrap_or(usize::MAX) })
+        .collect()
+}
+
+fn arg(args: &[usize], idx: usize, default: usize) -> usize {
+    args.get(idx).copied().filter(|v| *v != 0).unwrap_or(default)
+}
+
+fn next_tab(x: usize) -> usize {
+    (x / 8 + 1) * 8
+}
+
+// Applies the parameters of an SGR sequence to `style`.
+fn apply_sgr(style: &mut Style, args: &[usize]) {
+    let mut idx = 0;
+    while idx < args.len() {
+        match args[idx] {
+            // links are no SGR attribute and outlive a reset
+            0 => *style = Style { link: style.link.take(), ..Style::default() },
+            code @ 1..=9 => style.apply(&attribute(code)),
+            21 => style.apply(&Effect::under_line(0)),
+            22 => style.add_modifier.remove(Modifier::BOLD | Modifier::DIM),
+            23 => style.add_modifier.remove(Modifier::ITALIC),
+            24 => style.add_modifier.remove(Modifier::UNDERLINE),
+            25 => style.add_modifier.remove(Modifier::BLINK | Modifier::RAPID_BLINK),
+            27 => style.add_modifier.remove(Modifier::REVERSE),
+            28 => style.add_modifier.remove(Modifier::HIDDEN),
+            29 => style.add_modifier.remove(Modifier::STRIKETHROUGH),
+            code @ 30..=37 => style.apply(&Effect::front_color(Color::ansi(code as u8 - 30), 0)),
+            code @ 90..=97 => style.apply(&Effect::front_color(Color::ansi(code as u8 - 82), 0)),
+            code @ 40..=47 => style.apply(&Effect::back_color(Color::ansi(code as u8 - 40), 0)),
+            code @ 100..=107 => style.apply(&Effect::back_color(Color::ansi(code as u8 - 92), 0)),
+            39 => style.front = None,
+            49 => style.back = None,
+            code @ (38 | 48) => {
+                let color = match args.get(idx + 1) {
+                    Some(5) => {
+                        idx += 2;
+                        args.get(idx).filter(|i| **i <= 255).map(|i| Color::indexed(*i as u8))
+                    }
+                    Some(2) => {
+                        idx += 4;
+                        args.get(idx - 2..=idx)
+                            .filter(|c| c.iter().all(|v| *v <= 255))
+                            .map(|c| Color::from_rgb(c[0] as u8, c[1] as u8, c[2] as u8))
+                    }
+                    _ => None,
+                };
+                match (color, code) {
+                    (Some(color), 38) => style.apply(&Effect::front_color(color, 0)),
+                    (Some(color), _) => style.apply(&Effect::back_color(color, 0)),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+        idx += 1;
+    }
+}
+
+fn attribute(code: usize) -> Effect {
+    match code {
+        1 => Effect::bold(0),
+        2 => Effect::thin(0),
+        3 => Effect::italic(0),
+        4 => Effect::under_line(0),
+        5 => Effect::bling(0),
+        6 => Effect::fast_bling(0),
+        7 => Effect::invert(0),
+        8 => Effect::hide(0),
+        _ => Effect::cancel(0),
+    }
+}
+
+// Splits text into runs of printable text and the single control chars
+// which move the cursor.
+fn split_controls(text: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut start = 0;
+    for (idx, ch) in text.char_indices() {
+        if matches!(ch, '\n' | '\r' | '\x08' | '\t') {
+            if start < idx {
+                parts.push(&text[start..idx]);
+            }
+            parts.push(&text[idx..idx + 1]);
+            start = idx + 1;
+        }
+    }
+    if start < text.len() {
+        parts.push(&text[start..]);
+    }
+    parts
+}
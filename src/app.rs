@@ -1,21 +1,216 @@
-use crate::buffer::Buffer;
+use std::borrow::Cow;
+use std::io;
 
-pub struct Window {
-    width: usize,
-    height: usize,
-    buffers: Vec<Buffer>
+use crate::backend::Backend;
+use crate::buffer::{Buffer, BufferView, Cursor, CursorShape, Rect};
+use crate::effect::{ColorDepth, Style};
+use crate::widget::widget::Widget;
+
+/// Draws `Buffer`s to a `Backend`, sending only the cells which changed
+/// since the previously rendered frame.
+pub struct Window<B: Backend> {
+    backend: B,
+    last: Option<Buffer>,
+    // bytes the last render saved by switching styles minimally
+    saved: usize,
+    // cursor as the last render left it, `None` before the first one
+    cursor: Option<Cursor>,
+    // whether frames are wrapped in synchronized updates
+    sync: bool,
+    // colors beyond it are mapped to the closest ones the terminal has
+    color_depth: ColorDepth,
 }
 
-impl Window {
-    pub fn new(width: usize, height: usize) -> Self {
-        return Window {
-            width: width as usize,
-            height: height as usize,
-            buffers: vec![]
+impl<B: Backend> Window<B> {
+    pub fn new(backend: B) -> Self {
+        let sync = backend.supports_sync();
+        let color_depth = backend.color_depth();
+        Window { backend, last: None, saved: 0, cursor: None, sync, color_depth }
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    pub fn size(&self) -> io::Result<(usize, usize)> {
+        self.backend.size()
+    }
+
+    /// Bytes the last `render` saved by changing only the parts of the style
+    /// which differ, compared to resetting and selecting every style anew.
+    pub fn saved_bytes(&self) -> usize {
+        self.saved
+    }
+
+    /// Whether every frame is wrapped in a synchronized update, so the
+    /// terminal shows it at once instead of while it arrives.
+    pub fn is_sync(&self) -> bool {
+        self.sync
+    }
+
+    /// Overrides what the backend detected about synchronized updates.
+    pub fn set_sync(&mut self, sync: bool) {
+        self.sync = sync;
+    }
+
+    pub fn color_depth(&self) -> ColorDepth {
+        self.color_depth
+    }
+
+    /// Overrides the color depth the backend detected.
+    pub fn set_color_depth(&mut self, depth: ColorDepth) {
+        if depth != self.color_depth {
+            self.color_depth = depth;
+            self.invalidate();
+        }
+    }
+
+    /// Forgets the previous frame so the next `render` repaints everything.
+    pub fn invalidate(&mut self) {
+        self.last = None;
+        self.cursor = None;
+    }
+
+    /// Lays out `widget` over the whole backend area and renders it.
+    pub fn draw(&mut self, widget: &mut dyn Widget) -> io::Result<usize> {
+        let (width, height) = self.size()?;
+        let mut buffer = Buffer::new(width, height);
+        widget.write_buffer(&mut BufferView::new(&mut buffer));
+        self.render(&mut buffer)
+    }
+
+    /// Sends the difference between `buffer` and the last frame to the
+    /// backend and returns the number of bytes written. A dirty or resized
+    /// buffer is repainted completely, otherwise only its damaged regions
+    /// are compared, so a buffer kept across frames must not be swapped for
+    /// another one without marking it dirty.
+    pub fn render(&mut self, buffer: &mut Buffer) -> io::Result<usize> {
+        let last = self.last.take().filter(|last| {
+            !buffer.is_dirty() && last.width() == buffer.width() && last.height() == buffer.height()
+        });
+
+        let mut written = 0;
+        if self.sync {
+            written += self.backend.begin_sync()?;
+        }
+        if last.is_none() {
+            written += self.backend.clear()?;
+        }
+
+        let mut style = Style::default();
+        let mut saved = 0;
+        let mut cursor: Option<(usize, usize)> = None;
+
+        let spans = if last.is_some() {
+            buffer.damaged_spans()
+        } else {
+            (0..buffer.height()).map(|y| Rect::new(0, y, buffer.width(), 1)).collect()
         };
+
+        for span in &spans {
+            let y = span.y;
+            for x in span.x..span.right() {
+                let cell = &buffer.row(y)[x];
+                if cell.width == 0 {
+                    continue;
+                }
+                if let Some(last) = &last {
+                    if last.row(y)[x] == *cell {
+                        continue;
+                    }
+                }
+
+                if cursor != Some((x, y)) {
+                    written += self.backend.goto(x, y)?;
+                }
+                let cell_style = match self.color_depth {
+                    ColorDepth::TrueColor => Cow::Borrowed(&cell.style),
+                    depth => Cow::Owned(cell.style.downgrade(depth)),
+                };
+                if *cell_style != style {
+                    saved += cell_style.to_seq().len().saturating_sub(cell_style.transition(&style).len());
+                    written += self.backend.set_style(&style, &cell_style)?;
+                    style = cell_style.into_owned();
+                }
+                written += self.backend.print(&cell.symbol)?;
+                cursor = Some((x + cell.width, y));
+            }
+        }
+
+        if !style.is_default() {
+            written += self.backend.set_style(&style, &Style::default())?;
+        }
+        written += self.apply_cursor(buffer.cursor(), cursor.is_some())?;
+        if self.sync {
+            written += self.backend.end_sync()?;
+        }
+        self.backend.flush()?;
+
+        buffer.clear_dirty();
+        buffer.clear_damage();
+        // only the damaged cells of the kept frame can be outdated
+        self.last = Some(match last {
+            Some(mut last) => {
+                for span in &spans {
+                    for x in span.x..span.right() {
+                        *last.cell_mut(x, span.y) = buffer.row(span.y)[x].clone();
+                    }
+                }
+                last.clear_damage();
+                last
+            }
+            None => buffer.clone(),
+        });
+        self.saved = saved;
+        Ok(written)
     }
 
-    pub fn render() {
-        
+    /// Gives the terminal its own cursor back: the default shape, shown.
+    /// This happens when the window is dropped, too.
+    pub fn restore_cursor(&mut self) -> io::Result<()> {
+        let Some(cursor) = self.cursor.take() else {
+            return Ok(());
+        };
+        if cursor.shape != CursorShape::Default {
+            self.backend.set_cursor_shape(CursorShape::Default)?;
+        }
+        if cursor.position.is_none() {
+            self.backend.show_cursor()?;
+        }
+        self.backend.flush()
+    }
+
+    // Puts the cursor where the frame wants it, `drawn` tells whether
+    // drawing cells has moved it.
+    fn apply_cursor(&mut self, wanted: Cursor, drawn: bool) -> io::Result<usize> {
+        let mut written = 0;
+        let last = self.cursor;
+
+        if let Some((x, y)) = wanted.position {
+            if drawn || last.and_then(|cursor| cursor.position) != wanted.position {
+                written += self.backend.goto(x, y)?;
+            }
+        }
+        // terminals start out with their default shape
+        if last.map_or(CursorShape::Default, |cursor| cursor.shape) != wanted.shape {
+            written += self.backend.set_cursor_shape(wanted.shape)?;
+        }
+        let visible = wanted.position.is_some();
+        if last.map(|cursor| cursor.position.is_some()) != Some(visible) {
+            written += if visible { self.backend.show_cursor()? } else { self.backend.hide_cursor()? };
+        }
+
+        self.cursor = Some(wanted);
+        Ok(written)
+    }
+}
+
+impl<B: Backend> Drop for Window<B> {
+    fn drop(&mut self) {
+        let _ = self.restore_cursor();
     }
 }
@@ -0,0 +1,83 @@
+use std::io;
+
+use crate::buffer::CursorShape;
+use crate::effect::Style;
+use super::Backend;
+
+/// Backend of a fixed size which collects everything written to it.
+pub struct MemoryBackend {
+    width: usize,
+    height: usize,
+    output: Vec<u8>,
+}
+
+impl MemoryBackend {
+    pub fn new(width: usize, height: usize) -> Self {
+        MemoryBackend { width, height, output: vec![] }
+    }
+
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+    }
+
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    /// Returns the collected bytes and starts over with an empty output.
+    pub fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.output)
+    }
+
+    fn emit(&mut self, seq: &str) -> io::Result<usize> {
+        self.output.extend_from_slice(seq.as_bytes());
+        Ok(seq.len())
+    }
+}
+
+impl Backend for MemoryBackend {
+    fn size(&self) -> io::Result<(usize, usize)> {
+        Ok((self.width, self.height))
+    }
+
+    fn goto(&mut self, x: usize, y: usize) -> io::Result<usize> {
+        self.emit(&format!("\x1b[{};{}H", y + 1, x + 1))
+    }
+
+    fn set_style(&mut self, from: &Style, to: &Style) -> io::Result<usize> {
+        self.emit(&to.transition(from))
+    }
+
+    fn print(&mut self, text: &str) -> io::Result<usize> {
+        self.emit(text)
+    }
+
+    fn clear(&mut self) -> io::Result<usize> {
+        self.emit("\x1b[2J")
+    }
+
+    fn show_cursor(&mut self) -> io::Result<usize> {
+        self.emit("\x1b[?25h")
+    }
+
+    fn hide_cursor(&mut self) -> io::Result<usize> {
+        self.emit("\x1b[?25l")
+    }
+
+    fn set_cursor_shape(&mut self, shape: CursorShape) -> io::Result<usize> {
+        self.emit(&shape.to_seq())
+    }
+
+    fn begin_sync(&mut self) -> io::Result<usize> {
+        self.emit("\x1b[?2026h")
+    }
+
+    fn end_sync(&mut self) -> io::Result<usize> {
+        self.emit("\x1b[?2026l")
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+use std::io;
+
+use crate::buffer::CursorShape;
+use crate::effect::{ColorDepth, Style};
+
+#[cfg(feature = "termion")]
+pub mod termion;
+pub mod memory;
+pub mod terminfo;
+pub mod test;
+
+/// Terminal the renderer draws to. Writing methods return the number of
+/// bytes they emitted.
+pub trait Backend {
+    /// Size of the drawable area as (width, height).
+    fn size(&self) -> io::Result<(usize, usize)>;
+    /// Moves the cursor to a zero based column and row.
+    fn goto(&mut self, x: usize, y: usize) -> io::Result<usize>;
+    /// Changes the style from `from`, which the terminal is in, to `to`.
+    fn set_style(&mut self, from: &Style, to: &Style) -> io::Result<usize>;
+    fn print(&mut self, text: &str) -> io::Result<usize>;
+    fn clear(&mut self) -> io::Result<usize>;
+    fn show_cursor(&mut self) -> io::Result<usize>;
+    fn hide_cursor(&mut self) -> io::Result<usize>;
+    fn set_cursor_shape(&mut self, shape: CursorShape) -> io::Result<usize>;
+    /// Starts a synchronized update (DEC mode 2026): the terminal holds
+    /// back what follows until `end_sync`, so no half drawn frame shows.
+    fn begin_sync(&mut self) -> io::Result<usize>;
+    fn end_sync(&mut self) -> io::Result<usize>;
+    /// Whether the terminal is known to support synchronized updates.
+    fn supports_sync(&self) -> bool {
+        false
+    }
+    /// Colors the terminal can show, richer ones are mapped down to them.
+    fn color_depth(&self) -> ColorDepth {
+        ColorDepth::TrueColor
+    }
+    fn flush(&mut self) -> io::Result<()>;
+}
+
+/// Guesses from `TERM` and `TERM_PROGRAM` whether the terminal implements
+/// synchronized updates. Unknown terminals are assumed not to.
+pub fn detect_sync(term: &str, term_program: &str) -> bool {
+    const TERMS: [&str; 6] = ["kitty", "foot", "alacritty", "wezterm", "contour", "ghostty"];
+    const PROGRAMS: [&str; 4] = ["WezTerm", "iTerm.app", "ghostty", "vscode"];
+    TERMS.iter().any(|name| term.contains(name)) || PROGRAMS.contains(&term_program)
+}
+
+/// Guesses the color depth from `COLORTERM`, `TERM` and the number of
+/// `colors` its terminfo entry declares, if it was found.
+pub fn detect_color_depth(colorterm: &str, term: &str, colors: Option<u32>) -> ColorDepth {
+    if matches!(colorterm, "truecolor" | "24bit") || term.ends_with("-direct") || colors.is_some_and(|n| n >= 1 << 24) {
+        ColorDepth::TrueColor
+    } else if term.contains("256color") || colors.is_some_and(|n| n >= 256) {
+        ColorDepth::Indexed256
+    } else if term.is_empty() || term == "dumb" || colors.is_some_and(|n| n < 8) {
+        ColorDepth::NoColor
+    } else {
+        ColorDepth::Ansi16
+    }
+}
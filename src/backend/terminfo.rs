@@ -0,0 +1,64 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+// magic numbers of compiled entries with 16 and 32 bit numbers
+const MAGIC_16: u16 = 0o432;
+const MAGIC_32: u16 = 0o1036;
+// position of `colors` among the numeric capabilities
+const COLORS: usize = 13;
+
+/// Number of colors the terminfo entry of `term` declares, `None` if the
+/// entry can't be found or read.
+pub fn colors(term: &str) -> Option<u32> {
+    if term.is_empty() || term.contains(['/', '\\']) {
+        return None;
+    }
+    let first = term.chars().next()?;
+    entry_dirs().into_iter()
+        .flat_map(|dir| [dir.join(first.to_string()).join(term), dir.join(format!("{:x}", first as u32)).join(term)])
+        .find_map(|path| fs::read(path).ok())
+        .and_then(|data| read_colors(&data))
+}
+
+/// The `colors` capability of a compiled terminfo entry. An entry without
+/// it declares no colors at all.
+pub fn read_colors(data: &[u8]) -> Option<u32> {
+    let short = |idx: usize| data.get(idx * 2..idx * 2 + 2).map(|b| u16::from_le_bytes([b[0], b[1]]));
+    let number_size = match short(0)? {
+        MAGIC_16 => 2,
+        MAGIC_32 => 4,
+        _ => return None,
+    };
+    let (names, bools, numbers) = (short(1)? as usize, short(2)? as usize, short(3)? as usize);
+    if COLORS >= numbers {
+        return Some(0);
+    }
+    // numbers start on an even offset
+    let start = (12 + names + bools + 1) & !1;
+    let at = start + COLORS * number_size;
+    let bytes = data.get(at..at + number_size)?;
+    let value = if number_size == 2 {
+        i16::from_le_bytes([bytes[0], bytes[1]]) as i32
+    } else {
+        i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    };
+    // negative numbers mark absent or cancelled capabilities
+    Some(value.max(0) as u32)
+}
+
+// Directories searched for entries, in the order ncurses uses.
+fn entry_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![];
+    if let Some(dir) = env::var_os("TERMINFO") {
+        dirs.push(PathBuf::from(dir));
+    }
+    if let Some(home) = env::var_os("HOME") {
+        dirs.push(PathBuf::from(home).join(".terminfo"));
+    }
+    if let Ok(list) = env::var("TERMINFO_DIRS") {
+        dirs.extend(list.split(':').filter(|dir| !dir.is_empty()).map(PathBuf::from));
+    }
+    dirs.extend(["/etc/terminfo", "/lib/terminfo", "/usr/share/terminfo"].map(PathBuf::from));
+    dirs
+}
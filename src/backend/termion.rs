@@ -0,0 +1,123 @@
+use std::env;
+use std::io::{self, Write};
+use std::os::unix::io::AsRawFd;
+
+use termion::{clear, cursor};
+
+use crate::buffer::CursorShape;
+use crate::effect::{ColorDepth, Style};
+use super::{Backend, detect_color_depth, detect_sync, terminfo};
+
+/// Backend writing escape sequences to any `Write`, such as a raw mode
+/// stdout or a log file. The size is taken from the controlling terminal
+/// unless a fixed one is given. Output is collected and handed to the writer
+/// in one call per flush.
+pub struct TermionBackend<W: Write> {
+    out: W,
+    pending: Vec<u8>,
+    tty: bool,
+    size: Option<(usize, usize)>,
+}
+
+impl<W: Write + AsRawFd> TermionBackend<W> {
+    /// Backend for a writer with a file descriptor, which is checked for
+    /// being a terminal.
+    pub fn new(out: W) -> Self {
+        let tty = termion::is_tty(&out);
+        TermionBackend::with_tty(out, tty)
+    }
+}
+
+impl<W: Write> TermionBackend<W> {
+    /// Backend for any writer, `tty` tells whether it ends up on a terminal.
+    pub fn with_tty(out: W, tty: bool) -> Self {
+        TermionBackend { out, pending: vec![], tty, size: None }
+    }
+
+    /// Backend of a fixed size for a writer which is no terminal, such as a
+    /// log file, so that no controlling terminal is needed.
+    pub fn with_size(out: W, width: usize, height: usize) -> Self {
+        TermionBackend { out, pending: vec![], tty: false, size: Some((width, height)) }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.out
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.out
+    }
+
+    fn emit(&mut self, seq: &str) -> io::Result<usize> {
+        self.pending.extend_from_slice(seq.as_bytes());
+        Ok(seq.len())
+    }
+}
+
+impl<W: Write> Backend for TermionBackend<W> {
+    fn size(&self) -> io::Result<(usize, usize)> {
+        if let Some(size) = self.size {
+            return Ok(size);
+        }
+        let (width, height) = termion::terminal_size()?;
+        Ok((width as usize, height as usize))
+    }
+
+    fn goto(&mut self, x: usize, y: usize) -> io::Result<usize> {
+        self.emit(&cursor::Goto(x as u16 + 1, y as u16 + 1).to_string())
+    }
+
+    fn set_style(&mut self, from: &Style, to: &Style) -> io::Result<usize> {
+        self.emit(&to.transition(from))
+    }
+
+    fn print(&mut self, text: &str) -> io::Result<usize> {
+        self.emit(text)
+    }
+
+    fn clear(&mut self) -> io::Result<usize> {
+        self.emit(clear::All.as_ref())
+    }
+
+    fn show_cursor(&mut self) -> io::Result<usize> {
+        self.emit(cursor::Show.as_ref())
+    }
+
+    fn hide_cursor(&mut self) -> io::Result<usize> {
+        self.emit(cursor::Hide.as_ref())
+    }
+
+    fn set_cursor_shape(&mut self, shape: CursorShape) -> io::Result<usize> {
+        self.emit(&shape.to_seq())
+    }
+
+    fn begin_sync(&mut self) -> io::Result<usize> {
+        self.emit("\x1b[?2026h")
+    }
+
+    fn end_sync(&mut self) -> io::Result<usize> {
+        self.emit("\x1b[?2026l")
+    }
+
+    fn supports_sync(&self) -> bool {
+        let var = |name| env::var(name).unwrap_or_default();
+        detect_sync(&var("TERM"), &var("TERM_PROGRAM"))
+    }
+
+    /// Detected from the environment. Colors are left out when `NO_COLOR`
+    /// is set or the writer is no terminal, such as a pipe or a file.
+    fn color_depth(&self) -> ColorDepth {
+        let var = |name| env::var(name).unwrap_or_default();
+        if !var("NO_COLOR").is_empty() || !self.tty {
+            return ColorDepth::NoColor;
+        }
+        let term = var("TERM");
+        detect_color_depth(&var("COLORTERM"), &term, terminfo::colors(&term))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.write_all(&self.pending)?;
+        self.pending.clear();
+        self.out.flush()
+    }
+}
@@ -0,0 +1,235 @@
+use std::{env, fs, io, path::Path};
+
+use crate::buffer::{Buffer, CursorShape, symbols};
+use crate::effect::Style;
+use super::Backend;
+
+/// Headless backend which applies everything written to it to a `Buffer`,
+/// so rendered frames can be checked cell by cell.
+pub struct TestBackend {
+    buffer: Buffer,
+    cursor: (usize, usize),
+    cursor_visible: bool,
+    cursor_shape: CursorShape,
+    style: Style,
+}
+
+impl TestBackend {
+    pub fn new(width: usize, height: usize) -> Self {
+        TestBackend {
+            buffer: Buffer::new(width, height),
+            cursor: (0, 0),
+            cursor_visible: true,
+            cursor_shape: CursorShape::Default,
+            style: Style::default(),
+        }
+    }
+
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    pub fn cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    pub fn cursor_shape(&self) -> CursorShape {
+        self.cursor_shape
+    }
+
+    /// Symbols of every row, joined with newlines.
+    pub fn text(&self) -> String {
+        let mut lines = vec![];
+        for y in 0..self.buffer.height() {
+            lines.push(self.buffer.row(y).iter().map(|cell| cell.symbol.as_str()).collect::<String>());
+        }
+        lines.join("\n")
+    }
+
+    /// Map of the styles in use: one character per column, `.` for the
+    /// default style and `a` to `z`, `A` to `Z`, `0` to `9` in order of first
+    /// appearance. More distinct styles than keys panic.
+    pub fn style_map(&self) -> (String, Vec<Style>) {
+        let mut styles: Vec<Style> = vec![];
+        let mut lines = vec![];
+
+        for y in 0..self.buffer.height() {
+            let mut line = String::new();
+            for cell in self.buffer.row(y) {
+                if cell.style.is_default() {
+                    line.push('.');
+                    continue;
+                }
+                let idx = match styles.iter().position(|style| *style == cell.style) {
+                    Some(idx) => idx,
+                    None => {
+                        styles.push(cell.style.clone());
+                        styles.len() - 1
+                    }
+                };
+                line.push(style_key(idx));
+            }
+            lines.push(line);
+        }
+
+        (lines.join("\n"), styles)
+    }
+
+    /// Text grid followed by the style map and its legend.
+    pub fn snapshot(&self) -> String {
+        let (map, styles) = self.style_map();
+        let mut snapshot = self.text() + "\n\n" + &map + "\n\n";
+        for (idx, style) in styles.iter().enumerate() {
+            snapshot += &format!("{}: {}\n", style_key(idx), style.to_seq().replace('\x1b', "\\e"));
+        }
+        snapshot
+    }
+
+    pub fn assert_text(&self, expected: &str) {
+        assert_lines("text", expected, &self.text());
+    }
+
+    /// Checks the style of every cell. `expected` is a map as described in
+    /// `style_map`, whose keys are looked up in `legend`.
+    pub fn assert_styles(&self, expected: &str, legend: &[(char, Style)]) {
+        let mut lines = vec![];
+        for y in 0..self.buffer.height() {
+            let mut line = String::new();
+            for cell in self.buffer.row(y) {
+                let key = legend.iter()
+                    .find(|(_, style)| *style == cell.style)
+                    .map(|(key, _)| *key)
+                    .unwrap_or(if cell.style.is_default() { '.' } else { '?' });
+                line.push(key);
+            }
+            lines.push(line);
+        }
+        assert_lines("styles", expected, &lines.join("\n"));
+    }
+
+    /// Compares the snapshot with a golden file. The file is (re)written
+    /// instead when `UPDATE_SNAPSHOTS` is set, a missing one panics so that
+    /// an uncommitted snapshot can't pass unnoticed.
+    pub fn assert_snapshot<P: AsRef<Path>>(&self, path: P) {
+        self.assert_snapshot_with(path, env::var_os("UPDATE_SNAPSHOTS").is_some());
+    }
+
+    /// `assert_snapshot` with the update mode given instead of taken from
+    /// the environment.
+    pub fn assert_snapshot_with<P: AsRef<Path>>(&self, path: P, update: bool) {
+        let path = path.as_ref();
+        let actual = self.snapshot();
+
+        if update {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).unwrap();
+            }
+            fs::write(path, &actual).unwrap();
+            return;
+        }
+
+        let expected = fs::read_to_string(path).unwrap_or_else(|err| {
+            panic!("can't read snapshot {}: {} (run with UPDATE_SNAPSHOTS=1 to create it)", path.display(), err)
+        });
+        assert_lines(&path.display().to_string(), &expected, &actual);
+    }
+}
+
+impl Backend for TestBackend {
+    fn size(&self) -> io::Result<(usize, usize)> {
+        Ok((self.buffer.width(), self.buffer.height()))
+    }
+
+    fn goto(&mut self, x: usize, y: usize) -> io::Result<usize> {
+        self.cursor = (x, y);
+        Ok(0)
+    }
+
+    fn set_style(&mut self, _from: &Style, to: &Style) -> io::Result<usize> {
+        self.style = to.clone();
+        Ok(0)
+    }
+
+    fn print(&mut self, text: &str) -> io::Result<usize> {
+        for (symbol, _) in symbols(text) {
+            let (x, y) = self.cursor;
+            self.cursor.0 += self.buffer.set_symbol(x, y, &symbol, &self.style);
+        }
+        Ok(0)
+    }
+
+    fn clear(&mut self) -> io::Result<usize> {
+        self.buffer = Buffer::new(self.buffer.width(), self.buffer.height());
+        Ok(0)
+    }
+
+    fn show_cursor(&mut self) -> io::Result<usize> {
+        self.cursor_visible = true;
+        Ok(0)
+    }
+
+    fn hide_cursor(&mut self) -> io::Result<usize> {
+        self.cursor_visible = false;
+        Ok(0)
+    }
+
+    fn set_cursor_shape(&mut self, shape: CursorShape) -> io::Result<usize> {
+        self.cursor_shape = shape;
+        Ok(0)
+    }
+
+    fn begin_sync(&mut self) -> io::Result<usize> {
+        Ok(0)
+    }
+
+    fn end_sync(&mut self) -> io::Result<usize> {
+        Ok(0)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn style_key(idx: usize) -> char {
+    const KEYS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+    assert!(idx < KEYS.len(), "style maps tell at most {} styles apart", KEYS.len());
+    KEYS[idx] as char
+}
+
+// Panics with a line by line diff when the two texts differ.
+fn assert_lines(what: &str, expected: &str, actual: &str) {
+    let expected = expected.trim_end_matches('\n');
+    let actual = actual.trim_end_matches('\n');
+    if expected == actual {
+        return;
+    }
+
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    let mut diff = String::new();
+
+    for y in 0..expected.len().max(actual.len()) {
+        match (expected.get(y), actual.get(y)) {
+            (Some(e), Some(a)) if e == a => diff += &format!("  {:>3} |{}|\n", y, e),
+            (e, a) => {
+                if let Some(e) = e {
+                    diff += &format!("- {:>3} |{}|\n", y, e);
+                }
+                if let Some(a) = a {
+                    diff += &format!("+ {:>3} |{}|\n", y, a);
+                }
+                if let (Some(e), Some(a)) = (e, a) {
+                    let col = e.chars().zip(a.chars()).take_while(|(e, a)| e == a).count();
+                    diff += &format!("        {}^ column {}\n", " ".repeat(col), col);
+                }
+            }
+        }
+    }
+
+    panic!("{} mismatch (- expected, + actual):\n{}", what, diff);
+}
@@ -12,6 +12,7 @@ pub struct BorderLine {
 }
 
 impl BorderLine {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         left: char,
         right: char,
@@ -1,146 +1,604 @@
 use std::iter;
 
-use crate::{effect::{Effect, EffectType, Color}, border::BorderLine};
-use unicode_width::{UnicodeWidthChar};
+use crate::{effect::{Effect, Color, Style}, border::BorderLine};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+// damaged regions kept apart before they are merged into their bounds
+const MAX_DAMAGE: usize = 32;
+
+/// One terminal column. A wide glyph occupies its own cell plus a following
+/// cell of width 0 which holds no symbol.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cell {
+    pub symbol: String,
+    pub width: usize,
+    pub style: Style,
+    /// Lets the cells of lower layers show through when composing.
+    pub transparent: bool,
+}
+
+impl Cell {
+    pub fn new(symbol: &str, width: usize, style: Style) -> Self {
+        Cell { symbol: symbol.to_string(), width, style, transparent: false }
+    }
+
+    pub fn transparent() -> Self {
+        Cell { transparent: true, ..Cell::default() }
+    }
+
+    pub fn blank(style: Style) -> Self {
+        Cell::new(" ", 1, style)
+    }
+
+    fn continuation(style: Style) -> Self {
+        Cell::new("", 0, style)
+    }
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell::blank(Style::default())
+    }
+}
+
+fn cell_count(width: usize, height: usize) -> usize {
+    width.checked_mul(height).unwrap_or_else(|| panic!("a buffer of {}x{} cells is too large", width, height))
+}
+
+/// Cursor shapes of the DECSCUSR sequence.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CursorShape {
+    /// Whatever the terminal is configured to show.
+    #[default]
+    Default,
+    BlinkingBlock,
+    SteadyBlock,
+    BlinkingUnderline,
+    SteadyUnderline,
+    BlinkingBar,
+    SteadyBar,
+}
 
+impl CursorShape {
+    pub fn to_seq(&self) -> String {
+        format!("\x1b[{} q", *self as u8)
+    }
+}
+
+/// Hardware cursor a frame wants once it is drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Cursor {
+    /// Where the cursor is shown, `None` hides it.
+    pub position: Option<(usize, usize)>,
+    pub shape: CursorShape,
+}
+
+#[derive(Clone)]
 pub struct Buffer {
     width: usize,
     height: usize,
-    text: Vec<String>,
-    pub effects: Vec<Vec<Effect>>
+    cells: Vec<Cell>,
+    clips: Vec<Rect>,
+    // what empty space is filled with
+    fill: Cell,
+    dirty: bool,
+    // regions written since the last `clear_damage`
+    damage: Vec<Rect>,
+    cursor: Cursor,
 }
 
 impl Buffer {
     pub fn new(width: usize, height: usize) -> Self {
-        let text = iter::repeat(" ".to_string().repeat(width)).take(height).into_iter().collect();
-        let effects: Vec<Vec<Effect>> = iter::repeat(Vec::new()).take(height).into_iter().collect();
-        Buffer { width: width, height: height, text: text, effects: effects }
+        Buffer::filled(width, height, Cell::default())
     }
 
-    pub fn push_rect_buffer(&mut self, buf: Vec<String>, effects: Vec<Vec<Effect>>, rect: Rect) {
-        for y in 0..rect.height {
-            let line_vec: Vec<char> = self.text[rect.y + y].chars().collect();
-
-            let start_width = rect.x;
-            let mut crt_len_start = 0;
-            let mut start_idx = 0;
-            let mut start_term = "";
-
-            let end_width = self.width - (rect.x + rect.width);
-            let mut crt_len_end = 0;
-            let mut end_idx = 0;
-            let mut end_term = "";
-
-            for i in 0..line_vec.len() {
-                let c_width = line_vec[i].width().unwrap_or(2);
-
-                if crt_len_start + c_width > start_width {
-                    start_idx = i;
-                    if c_width == 2 && crt_len_start + c_width == start_width + 1{
-                        start_term = " ";
-                    }
-                    break;
+    /// Buffer whose cells are all transparent, to be drawn over others.
+    pub fn transparent(width: usize, height: usize) -> Self {
+        Buffer::filled(width, height, Cell::transparent())
+    }
+
+    fn filled(width: usize, height: usize, fill: Cell) -> Self {
+        let mut buffer = Buffer { width, height, cells: vec![fill.clone(); cell_count(width, height)], clips: vec![], fill, dirty: false, damage: vec![], cursor: Cursor::default() };
+        buffer.mark_damaged(buffer.area());
+        buffer
+    }
+
+    /// Changes the size, keeping the cells which still fit. New space is
+    /// blank and a wide glyph cut by the new right edge becomes padding.
+    /// The buffer is marked dirty.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        if width == self.width && height == self.height {
+            return;
+        }
+
+        let mut cells = vec![self.fill.clone(); cell_count(width, height)];
+        let keep = self.width.min(width);
+        for y in 0..self.height.min(height) {
+            cells[y * width..y * width + keep].clone_from_slice(&self.row(y)[..keep]);
+            if keep > 0 && cells[y * width + keep - 1].width > 1 {
+                let cell = &mut cells[y * width + keep - 1];
+                *cell = Cell { transparent: cell.transparent, ..Cell::blank(cell.style.clone()) };
+            }
+        }
+
+        self.width = width;
+        self.height = height;
+        self.cells = cells;
+        self.mark_dirty();
+        if self.cursor.position.is_some_and(|(x, y)| x >= width || y >= height) {
+            self.hide_cursor();
+        }
+        self.damage.clear();
+        self.mark_damaged(self.area());
+    }
+
+    /// Flags the whole buffer to be repainted by the next render.
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn clear_dirty(&mut self) {
+        self.dirty = false;
+    }
+
+    /// Cursor to show after the frame is drawn. It is hidden by default.
+    pub fn cursor(&self) -> Cursor {
+        self.cursor
+    }
+
+    /// Shows the cursor at the given position, or hides it when the
+    /// position is outside the buffer.
+    pub fn set_cursor(&mut self, x: usize, y: usize) {
+        self.cursor.position = Some((x, y)).filter(|_| x < self.width && y < self.height);
+    }
+
+    pub fn hide_cursor(&mut self) {
+        self.cursor.position = None;
+    }
+
+    pub fn set_cursor_shape(&mut self, shape: CursorShape) {
+        self.cursor.shape = shape;
+    }
+
+    /// Regions written to since the damage was last cleared. A new or
+    /// resized buffer is damaged all over.
+    pub fn damage(&self) -> &[Rect] {
+        &self.damage
+    }
+
+    /// Records that the cells in `rect` may have changed.
+    pub fn mark_damaged(&mut self, rect: Rect) {
+        let rect = rect.intersection(&self.area());
+        if rect.is_empty() || self.damage.iter().any(|damage| damage.intersection(&rect) == rect) {
+            return;
+        }
+        self.damage.retain(|damage| rect.intersection(damage) != *damage);
+        self.damage.push(rect);
+
+        // many small regions cost more to walk than they save
+        if self.damage.len() > MAX_DAMAGE {
+            let bounds = self.damage.iter().fold(rect, |bounds, damage| bounds.union(damage));
+            self.damage = vec![bounds];
+        }
+    }
+
+    pub fn clear_damage(&mut self) {
+        self.damage.clear();
+    }
+
+    /// The damaged cells as one row high spans, sorted from the top left and
+    /// without overlaps, so that renderers can serialize just those.
+    pub fn damaged_spans(&self) -> Vec<Rect> {
+        let mut spans: Vec<Rect> = vec![];
+        for y in 0..self.height {
+            let mut row: Vec<Rect> = self.damage.iter()
+                .filter(|damage| y >= damage.y && y < damage.bottom())
+                .map(|damage| Rect::new(damage.x, y, damage.width, 1))
+                .collect();
+            row.sort_by_key(|span| span.x);
+
+            let mut merged: Vec<Rect> = vec![];
+            for span in row {
+                match merged.last_mut() {
+                    Some(last) if span.x <= last.right() => *last = last.union(&span),
+                    _ => merged.push(span),
                 }
-                crt_len_start += c_width;
             }
+            spans.append(&mut merged);
+        }
+        spans
+    }
+
+    // Damage of a write to `rect`. Wide glyphs cut at its edges are padded,
+    // so the columns beside it can change too.
+    fn touch(&mut self, rect: Rect) {
+        let x = rect.x.saturating_sub(1);
+        self.mark_damaged(Rect::new(x, rect.y, rect.right() + 1 - x, rect.height));
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
 
-            for i in (0..line_vec.len()).rev() {
-                let c_width = line_vec[i].width().unwrap_or(2);
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn area(&self) -> Rect {
+        Rect::new(0, 0, self.width, self.height)
+    }
 
-                if crt_len_end + c_width > end_width {
-                    end_idx = i + 1;
-                    if c_width == 2 && crt_len_end + c_width == end_width + 1{
-                        end_term = " ";
+    pub fn row(&self, y: usize) -> &[Cell] {
+        &self.cells[y * self.width..(y + 1) * self.width]
+    }
+
+    pub(crate) fn cell_mut(&mut self, x: usize, y: usize) -> &mut Cell {
+        self.mark_damaged(Rect::new(x, y, 1, 1));
+        let idx = self.index_of(x, y);
+        &mut self.cells[idx]
+    }
+
+    fn index_of(&self, x: usize, y: usize) -> usize {
+        y * self.width + x
+    }
+
+    /// Area writes are currently limited to.
+    pub fn clip_rect(&self) -> Rect {
+        match self.clips.last() {
+            Some(clip) => clip.intersection(&self.area()),
+            None => self.area(),
+        }
+    }
+
+    /// Limits all following writes to `rect` inside the current clip until
+    /// the matching `pop_clip`.
+    pub fn push_clip(&mut self, rect: Rect) {
+        let clip = self.clip_rect().intersection(&rect);
+        self.clips.push(clip);
+    }
+
+    pub fn pop_clip(&mut self) {
+        self.clips.pop();
+    }
+
+    pub fn push_rect_buffer(&mut self, buf: Vec<String>, effects: Vec<Vec<Effect>>, rect: Rect) {
+        let area = rect.intersection(&self.clip_rect());
+        if area.is_empty() {
+            return;
+        }
+        self.touch(area);
+
+        for row in area.y..area.bottom() {
+            let y = row - rect.y;
+            self.split_wide_cell(area.x, row);
+            self.split_wide_cell(area.right(), row);
+
+            // styles inherit whatever was active at the left edge of the rect,
+            // except a link which would swallow the new text
+            let mut style = Style { link: None, ..self.cells[self.index_of(area.x, row)].style.clone() };
+            let line = buf.get(y).map(|l| l.as_str()).unwrap_or("");
+            let line_effs = effects.get(y).map(|v| v.as_slice()).unwrap_or(&[]);
+            let mut eff_idx = 0;
+            let mut symbols = symbols(line).into_iter();
+            let mut x = 0;
+
+            while x < rect.width && rect.x + x < area.right() {
+                while eff_idx < line_effs.len() && line_effs[eff_idx].pos <= x {
+                    style.apply(&line_effs[eff_idx]);
+                    eff_idx += 1;
+                }
+
+                let (symbol, s_width) = symbols.next().unwrap_or_else(|| (" ".to_string(), 1));
+                let col = rect.x + x;
+                let end = (col + s_width).min(area.right());
+                if col >= area.x && x + s_width <= rect.width && end == col + s_width {
+                    let idx = self.index_of(col, row);
+                    self.cells[idx] = Cell::new(&symbol, s_width, style.clone());
+                    for i in 1..s_width {
+                        self.cells[idx + i] = Cell::continuation(style.clone());
+                    }
+                } else {
+                    // the glyph sticks out of the rect or the clip
+                    for col in col.max(area.x)..end {
+                        let idx = self.index_of(col, row);
+                        self.cells[idx] = Cell::blank(style.clone());
                     }
-                    break;
                 }
-                crt_len_end += c_width;
+                x += s_width;
             }
+        }
+    }
 
-            self.text[rect.y + y] = line_vec[..start_idx].into_iter().collect::<String>() +
-                                    start_term +
-                                    &buf[y] +
-                                    end_term +
-                                    &line_vec[end_idx..].into_iter().collect::<String>();
-            
-            // push effect vec to buffer !!
-            let mut ori_eff_vec = self.effects[rect.y + y].clone();
-
-            let mut eff_vec_start: Vec<Effect> = vec![];
-            let mut eff_vec_end: Vec<Effect> = vec![];
-            
-            for i in 0..ori_eff_vec.len() {
-                if ori_eff_vec[i].pos >= rect.x {
-                    eff_vec_start = ori_eff_vec[..i].to_vec();
-                    break;
-                }
+    pub fn get_cell(&self, x: usize, y: usize) -> Option<&Cell> {
+        if x < self.width && y < self.height {
+            Some(&self.cells[self.index_of(x, y)])
+        } else {
+            None
+        }
+    }
+
+    /// Writes `text` from the given position on and returns the columns it
+    /// took. Writing stops before the first glyph crossing the right edge of
+    /// the clip. Columns left of the clip don't count, and the visible part of
+    /// a glyph crossing the left edge of the clip becomes padding.
+    pub fn set_string(&mut self, x: usize, y: usize, text: &str, style: &Style) -> usize {
+        self.set_string_truncated(x, y, text, usize::MAX, style)
+    }
+
+    /// `set_string` which takes at most `max_width` columns. A glyph which
+    /// would cross the limit isn't written.
+    pub fn set_string_truncated(&mut self, x: usize, y: usize, text: &str, max_width: usize, style: &Style) -> usize {
+        let clip = self.clip_rect();
+        if y < clip.y || y >= clip.bottom() {
+            return 0;
+        }
+        let right = clip.right().min(x.saturating_add(max_width));
+        let (mut col, mut written) = (x, 0);
+        for (symbol, width) in symbols(text) {
+            if col.saturating_add(width) > right {
+                break;
             }
+            written += (col + width).saturating_sub(col.max(clip.x));
+            col += self.set_symbol(col, y, &symbol, style);
+        }
+        written
+    }
 
-            let mut child_effs = effects[y].clone();
-            for i in 0..child_effs.len() {
-                child_effs[i].pos += rect.x;
+    /// Patches the style of every cell in `rect` with `style`, see
+    /// `Style::patch`, keeping the symbols.
+    pub fn set_style(&mut self, rect: Rect, style: &Style) {
+        let area = rect.intersection(&self.clip_rect());
+        self.mark_damaged(area);
+        for y in area.y..area.bottom() {
+            for x in area.x..area.right() {
+                let idx = self.index_of(x, y);
+                self.cells[idx].style = self.cells[idx].style.clone().patch(style);
             }
+        }
+    }
 
-            for i in (0..ori_eff_vec.len()).rev() { 
-                if ori_eff_vec[i].kind == EffectType::Reset && ori_eff_vec[i].pos <= rect.x + rect.width {
-                    eff_vec_end = ori_eff_vec[i + 1..].to_vec();
-                    break;
+    /// Covers `rect` with `symbol`. A wide symbol is repeated every two
+    /// columns and padding fills a column left over.
+    pub fn fill(&mut self, rect: Rect, symbol: char, style: &Style) {
+        self.push_clip(rect);
+        let area = self.clip_rect();
+        let symbol = symbol.to_string();
+        for y in area.y..area.bottom() {
+            let mut x = area.x;
+            while x < area.right() {
+                x += self.set_symbol(x, y, &symbol, style).max(1);
+            }
+        }
+        self.pop_clip();
+    }
 
-                } else if ori_eff_vec[i].pos <= rect.x + rect.width {
-                    ori_eff_vec[i].pos = rect.x + rect.width;
-                }
-                if i == 0 {
-                    eff_vec_end = ori_eff_vec.clone();
-                }
+    /// Puts a single symbol at the given position and returns its width.
+    /// The part of a glyph which sticks out of the clip is cut and the rest
+    /// replaced with padding.
+    pub(crate) fn set_symbol(&mut self, x: usize, y: usize, symbol: &str, style: &Style) -> usize {
+        let width = symbol_width(symbol);
+        let clip = self.clip_rect();
+        let (start, end) = (x.max(clip.x), x.saturating_add(width).min(clip.right()));
+        if width == 0 || y < clip.y || y >= clip.bottom() || start >= end {
+            return width;
+        }
+        if start > x || end < x + width {
+            self.touch(Rect::new(start, y, end - start, 1));
+            self.split_wide_cell(start, y);
+            self.split_wide_cell(end, y);
+            for x in start..end {
+                let idx = self.index_of(x, y);
+                self.cells[idx] = Cell::blank(style.clone());
             }
+            return width;
+        }
+        self.put_cell(x, y, &Cell::new(symbol, width, style.clone()));
+        width
+    }
+
+    // Places `cell` and the continuation cells it needs, ignoring the clip.
+    pub(crate) fn put_cell(&mut self, x: usize, y: usize, cell: &Cell) {
+        if cell.width == 0 || y >= self.height || x + cell.width > self.width {
+            return;
+        }
+        self.touch(Rect::new(x, y, cell.width, 1));
+        self.split_wide_cell(x, y);
+        self.split_wide_cell(x + cell.width, y);
+
+        let idx = self.index_of(x, y);
+        self.cells[idx] = cell.clone();
+        for i in 1..cell.width {
+            self.cells[idx + i] = Cell { transparent: cell.transparent, ..Cell::continuation(cell.style.clone()) };
+        }
+    }
 
-            self.effects[rect.y + y] = vec![];
-            self.effects[rect.y + y].append(&mut eff_vec_start.clone());
-            self.effects[rect.y + y].append(&mut child_effs.clone());
-            self.effects[rect.y + y].append(&mut eff_vec_end.clone());
+    // Turns a wide glyph straddling column `x` into padding so that writes
+    // starting at `x` never leave half a glyph behind.
+    fn split_wide_cell(&mut self, x: usize, y: usize) {
+        if x == 0 || x >= self.width || self.cells[self.index_of(x, y)].width != 0 {
+            return;
+        }
+        let mut head = x;
+        while head > 0 && self.cells[self.index_of(head, y)].width == 0 {
+            head -= 1;
+        }
+        let head_idx = self.index_of(head, y);
+        let end = head_idx + self.cells[head_idx].width.max(1);
+        for idx in head_idx..end {
+            let style = self.cells[idx].style.clone();
+            self.cells[idx] = Cell::blank(style);
         }
     }
 
     pub fn get_buffer(&self) -> Vec<String> {
+        self.get_rect(self.area())
+    }
+
+    /// Serializes the lines of `rect` like `get_buffer`, for instance the
+    /// `damaged_spans` only. A wide glyph starting left of the rect is left
+    /// out, one sticking out on the right is included.
+    pub fn get_rect(&self, rect: Rect) -> Vec<String> {
+        let columns = rect.x.min(self.width)..rect.right().min(self.width);
         let mut view_buf = vec![];
 
-        for y in 0..self.text.len() {
-            let line_chars = self.text[y].chars().collect::<Vec<char>>();
-            let line_effs = self.effects[y].clone();
-            let mut line_text = "".to_string();
-            let mut crt_line_len = 0;
-            let mut eff_idx = 0;
+        for y in rect.y.min(self.height)..rect.bottom().min(self.height) {
+            let mut line_text = String::new();
+            let mut style = Style::default();
 
-            for ch in line_chars {
-                for i in eff_idx..line_effs.len() {
-                    if line_effs[i].pos == crt_line_len {
-                        line_text += &line_effs[i].to_string();
-                    } else if line_effs[i].pos > crt_line_len {
-                        eff_idx = i;
-                        break;
-                    }
+            for cell in &self.row(y)[columns.clone()] {
+                if cell.width == 0 {
+                    continue;
                 }
-                line_text += &ch.to_string();
-                crt_line_len += ch.width().unwrap_or(2);
-            }
-
-            for i in eff_idx..line_effs.len() {
-                if line_effs[i].pos == crt_line_len {
-                    line_text += &line_effs[i].to_string();
-                } else if line_effs[i].pos > crt_line_len {
-                    eff_idx = i;
+                if cell.style != style {
+                    line_text += &cell.style.transition(&style);
+                    style = cell.style.clone();
                 }
+                line_text += &cell.symbol;
             }
 
+            if !style.is_default() {
+                line_text += &Style::default().transition(&style);
+            }
             view_buf.push(line_text);
         }
 
-        return view_buf;
+        view_buf
+    }
+}
+
+/// Part of a `Buffer` handed to a widget. Coordinates are local, (0, 0) is
+/// the top left corner of the view, and writes never leave its area.
+pub struct BufferView<'a> {
+    buffer: &'a mut Buffer,
+    // absolute area, always inside the area of the parent view
+    area: Rect,
+}
+
+impl<'a> BufferView<'a> {
+    /// View of the whole buffer.
+    pub fn new(buffer: &'a mut Buffer) -> Self {
+        let area = buffer.area();
+        BufferView { buffer, area }
+    }
+
+    pub fn width(&self) -> usize {
+        self.area.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.area.height
+    }
+
+    /// The local area of the view, at (0, 0).
+    pub fn area(&self) -> Rect {
+        Rect::new(0, 0, self.area.width, self.area.height)
+    }
+
+    /// Position of the view in the buffer.
+    pub fn absolute_area(&self) -> Rect {
+        self.area
+    }
+
+    /// View of the local `rect`, cut to this view, for drawing a child.
+    pub fn sub_view(&mut self, rect: Rect) -> BufferView<'_> {
+        let area = self.to_absolute(rect).intersection(&self.area);
+        BufferView { buffer: self.buffer, area }
+    }
+
+    /// `Buffer::push_rect_buffer` at the local `rect`.
+    pub fn push_rect_buffer(&mut self, buf: Vec<String>, effects: Vec<Vec<Effect>>, rect: Rect) {
+        let rect = self.to_absolute(rect);
+        self.buffer.push_clip(self.area);
+        self.buffer.push_rect_buffer(buf, effects, rect);
+        self.buffer.pop_clip();
+    }
+
+    /// Shows the cursor at a local position inside the view.
+    pub fn set_cursor(&mut self, x: usize, y: usize) {
+        if self.area().contains(x, y) {
+            self.buffer.set_cursor(self.area.x + x, self.area.y + y);
+        }
+    }
+
+    pub fn set_cursor_shape(&mut self, shape: CursorShape) {
+        self.buffer.set_cursor_shape(shape);
+    }
+
+    /// `Buffer::get_cell` at a local position, `None` outside the view.
+    pub fn get_cell(&self, x: usize, y: usize) -> Option<&Cell> {
+        if !self.area().contains(x, y) {
+            return None;
+        }
+        self.buffer.get_cell(self.area.x + x, self.area.y + y)
+    }
+
+    /// `Buffer::set_string` at a local position, stopping at the edge of the
+    /// view.
+    pub fn set_string(&mut self, x: usize, y: usize, text: &str, style: &Style) -> usize {
+        self.set_string_truncated(x, y, text, usize::MAX, style)
+    }
+
+    pub fn set_string_truncated(&mut self, x: usize, y: usize, text: &str, max_width: usize, style: &Style) -> usize {
+        let (x, y) = (self.area.x.saturating_add(x), self.area.y.saturating_add(y));
+        self.buffer.push_clip(self.area);
+        let width = self.buffer.set_string_truncated(x, y, text, max_width, style);
+        self.buffer.pop_clip();
+        width
+    }
+
+    pub fn set_style(&mut self, rect: Rect, style: &Style) {
+        let rect = self.to_absolute(rect).intersection(&self.area);
+        self.buffer.set_style(rect, style);
+    }
+
+    pub fn fill(&mut self, rect: Rect, symbol: char, style: &Style) {
+        let rect = self.to_absolute(rect);
+        self.buffer.push_clip(self.area);
+        self.buffer.fill(rect, symbol, style);
+        self.buffer.pop_clip();
+    }
+
+    fn to_absolute(&self, rect: Rect) -> Rect {
+        Rect::new(self.area.x.saturating_add(rect.x), self.area.y.saturating_add(rect.y), rect.width, rect.height)
+    }
+}
 
+/// Display width of a single grapheme cluster. Control characters take
+/// no space and nothing is wider than two columns.
+pub fn symbol_width(symbol: &str) -> usize {
+    if symbol.chars().any(|c| c.is_control()) {
+        let visible: String = symbol.chars().filter(|c| !c.is_control()).collect();
+        return visible.width().min(2);
     }
+    symbol.width().min(2)
 }
 
-#[derive(Clone, Copy)]
+/// Splits `text` into grapheme clusters paired with their display width.
+/// Control characters are dropped and clusters without width are joined to
+/// the preceding cluster.
+pub fn symbols(text: &str) -> Vec<(String, usize)> {
+    let mut symbols: Vec<(String, usize)> = vec![];
+    for cluster in text.graphemes(true) {
+        let visible: String = cluster.chars().filter(|c| !c.is_control()).collect();
+        if visible.is_empty() {
+            continue;
+        }
+        let width = symbol_width(&visible);
+        match symbols.last_mut() {
+            Some(last) if width == 0 => last.0 += &visible,
+            _ if width == 0 => {}
+            _ => symbols.push((visible, width)),
+        }
+    }
+    symbols
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Rect {
     pub x: usize,
     pub y: usize,
@@ -150,54 +608,86 @@ pub struct Rect {
 
 impl Rect {
     pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
-        Rect { x: x, y: y, width: width, height: height }
+        Rect { x, y, width, height }
     }
 
-    pub fn can_draw_border(&self) -> bool {
-        if self.width >= 3 && self.height >= 3 {
-            return true;
-        } else {
-            return false;
+    pub fn right(&self) -> usize {
+        self.x.saturating_add(self.width)
+    }
+
+    pub fn bottom(&self) -> usize {
+        self.y.saturating_add(self.height)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && x < self.right() && y >= self.y && y < self.bottom()
+    }
+
+    /// Smallest rect covering both.
+    pub fn union(&self, other: &Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rect::new(x, y, self.right().max(other.right()) - x, self.bottom().max(other.bottom()) - y)
+    }
+
+    /// Overlapping part of both rects, empty when they don't overlap.
+    pub fn intersection(&self, other: &Rect) -> Rect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+        if right <= x || bottom <= y {
+            return Rect::new(x, y, 0, 0);
         }
+        Rect::new(x, y, right - x, bottom - y)
+    }
+
+    pub fn can_draw_border(&self) -> bool {
+        self.width >= 3 && self.height >= 3
     }
 
     pub fn make_bordered_rect(&self, line: Option<BorderLine>) -> Rect {
         if self.can_draw_border() && line.is_some() {
-            let mut rect = self.clone();
+            let mut rect = *self;
             rect.x += 1;
             rect.y += 1;
             rect.width -= 2;
             rect.height -= 2;
-            return rect;
+            rect
         } else {
-            return self.clone();
+            *self
         }
     }
 
     pub fn make_border_buf(&self, line: Option<BorderLine>) -> Vec<String> {
-        if line.is_none() || !self.can_draw_border() {
-            return iter::repeat(" ".to_string().repeat(self.width)).take(self.height).into_iter().collect();
-        } else {
-            let border = line.unwrap();
-            let mut buf: Vec<String> = iter::repeat(
-                border.l.to_string() +
-                &" ".to_string().repeat(self.width - 2) +
-                &border.r.to_string()
-            ).take(self.height - 2).into_iter().collect();
-
-            buf.insert(
-                0,
-                border.lt.to_string() +
-                &border.t.to_string().repeat(self.width - 2) +
-                &border.rt.to_string()
-            );
-            buf.push(
-                border.lb.to_string() +
-                &border.b.to_string().repeat(self.width - 2) +
-                &border.rb.to_string()
-            );
-
-            return buf;
+        match line {
+            Some(border) if self.can_draw_border() => {
+                let mut buf: Vec<String> = iter::repeat_n(
+                    border.l.to_string() +
+                    &" ".repeat(self.width - 2) +
+                    &border.r.to_string(),
+                    self.height - 2
+                ).collect();
+
+                buf.insert(
+                    0,
+                    border.lt.to_string() +
+                    &border.t.to_string().repeat(self.width - 2) +
+                    &border.rt.to_string()
+                );
+                buf.push(
+                    border.lb.to_string() +
+                    &border.b.to_string().repeat(self.width - 2) +
+                    &border.rb.to_string()
+                );
+
+                buf
+            }
+            _ => iter::repeat_n(" ".repeat(self.width), self.height).collect(),
         }
     }
 
@@ -214,6 +704,12 @@ impl Rect {
         line_eff_vec.append(&mut eff_vec);
         line_eff_vec.push(Effect::reset(self.width));
 
-        return iter::repeat(line_eff_vec).take(self.height).into_iter().collect();
+        iter::repeat_n(line_eff_vec, self.height).collect()
     }
-}
\ No newline at end of file
+
+    /// Like `make_color_eff_vec`, with black or white text, whichever is
+    /// more readable on `back`.
+    pub fn make_contrast_eff_vec(&self, back: Color) -> Vec<Vec<Effect>> {
+        self.make_color_eff_vec(Some(back.contrast_text()).filter(|front| *front != Color::Reset), Some(back))
+    }
+}
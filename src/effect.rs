@@ -1,35 +1,378 @@
-#[derive(Clone)]
-pub struct Color {
-    pub code: String
+use std::error::Error;
+use std::fmt;
+use std::ops::{BitAnd, BitOr, BitOrAssign, Not, Sub};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+// channel values of the 6x6x6 color cube of 256 color terminals
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+// xterm defaults of the first 16 palette entries
+const ANSI_COLORS: [(u8, u8, u8); 16] = [
+    (0, 0, 0), (205, 0, 0), (0, 205, 0), (205, 205, 0),
+    (0, 0, 238), (205, 0, 205), (0, 205, 205), (229, 229, 229),
+    (127, 127, 127), (255, 0, 0), (0, 255, 0), (255, 255, 0),
+    (92, 92, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255),
+];
+
+/// Terminal color. The named colors and `Indexed` entries come from the
+/// terminal's palette and so follow its theme, `Rgb` is exact.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Color {
+    /// The terminal's default color.
+    Reset,
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    BrightBlack,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightMagenta,
+    BrightCyan,
+    BrightWhite,
+    /// Entry of the 256 color palette.
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+    /// Translucent color, alpha 0 is fully transparent and 255 opaque. It
+    /// blends with the colors below when layers are composed.
+    Rgba(u8, u8, u8, u8),
+}
+
+// the 16 named colors in palette order
+const NAMED: [Color; 16] = [
+    Color::Black, Color::Red, Color::Green, Color::Yellow,
+    Color::Blue, Color::Magenta, Color::Cyan, Color::White,
+    Color::BrightBlack, Color::BrightRed, Color::BrightGreen, Color::BrightYellow,
+    Color::BrightBlue, Color::BrightMagenta, Color::BrightCyan, Color::BrightWhite,
+];
+
+/// How many colors a terminal can show, from none to 24 bit RGB.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ColorDepth {
+    NoColor,
+    /// The 16 named colors.
+    Ansi16,
+    /// The 256 color palette.
+    Indexed256,
+    TrueColor,
 }
 
 impl Color {
     pub fn from_rgb(r: u8, g: u8, b: u8) -> Self {
-        Color { code: format!("2;{};{};{}m", r, g, b) }
+        Color::Rgb(r, g, b)
+    }
+
+    /// Translucent color, `alpha` 0 is fully transparent and 255 opaque.
+    pub fn from_rgba(r: u8, g: u8, b: u8, alpha: u8) -> Self {
+        if alpha == 255 {
+            Color::Rgb(r, g, b)
+        } else {
+            Color::Rgba(r, g, b, alpha)
+        }
     }
 
     pub fn from_rgb_8bit(r: u8, g: u8, b: u8) -> Self {
         assert!(r <= 5, "The red color value is too big. (r <= 5)");
         assert!(g <= 5, "The green color value is too big. (g <= 5)");
         assert!(b <= 5, "The blue color value is too big. (b <= 5)");
-        Color { code: format!("5;{}m", 16 + r * 36 + g * 6 + b) }
+        Color::indexed(16 + r * 36 + g * 6 + b)
     }
 
     pub fn gray(brightness: u8) -> Self {
         assert!(brightness <= 23, "The brightness value is too big. (0 - 23)");
-        Color { code: format!("5;{}m", 232 + brightness) }
+        Color::indexed(232 + brightness)
+    }
+
+    pub fn indexed(index: u8) -> Self {
+        Color::Indexed(index)
+    }
+
+    /// Named color of the first 16 palette entries, as SGR 30-37 and 90-97
+    /// select them.
+    pub fn ansi(index: u8) -> Self {
+        assert!(index <= 15, "The ANSI color index is too big. (0 - 15)");
+        NAMED[index as usize]
+    }
+
+    /// This color with `alpha`. A translucent palette color turns into its
+    /// default components, `Reset` can't be translucent and stays.
+    pub fn with_alpha(self, alpha: u8) -> Self {
+        match self.rgb() {
+            Some((r, g, b)) if alpha < 255 || matches!(self, Color::Rgba(..)) => Color::from_rgba(r, g, b, alpha),
+            _ => self,
+        }
+    }
+
+    /// Components of the color, palette entries as xterm shows them by
+    /// default. `Reset` has none, the terminal decides.
+    pub fn rgb(&self) -> Option<(u8, u8, u8)> {
+        match *self {
+            Color::Reset => None,
+            Color::Indexed(index @ 0..=15) => Some(ANSI_COLORS[index as usize]),
+            Color::Indexed(index @ 16..=231) => {
+                let cube = index - 16;
+                Some((CUBE_LEVELS[(cube / 36) as usize], CUBE_LEVELS[(cube / 6 % 6) as usize], CUBE_LEVELS[(cube % 6) as usize]))
+            }
+            Color::Indexed(index) => {
+                let level = 8 + (index - 232) * 10;
+                Some((level, level, level))
+            }
+            Color::Rgb(r, g, b) | Color::Rgba(r, g, b, _) => Some((r, g, b)),
+            named => named.ansi_index().map(|index| ANSI_COLORS[index as usize]),
+        }
+    }
+
+    pub fn alpha(&self) -> u8 {
+        match self {
+            Color::Rgba(_, _, _, alpha) => *alpha,
+            _ => 255,
+        }
+    }
+
+    pub fn is_opaque(&self) -> bool {
+        self.alpha() == 255
+    }
+
+    /// Composites this color over `under` ("source over" blending). Over
+    /// `Reset` nothing is known to blend with and the color stays.
+    pub fn blend_over(&self, under: &Color) -> Color {
+        let (Some((r, g, b)), Some((ur, ug, ub))) = (self.rgb(), under.rgb()) else {
+            return *self;
+        };
+        if self.is_opaque() {
+            return *self;
+        }
+        let top_a = self.alpha() as u32;
+        let under_a = under.alpha() as u32 * (255 - top_a) / 255;
+        let alpha = top_a + under_a;
+        if alpha == 0 {
+            return Color::from_rgba(0, 0, 0, 0);
+        }
+
+        let mix = |top: u8, under: u8| ((top as u32 * top_a + under as u32 * under_a + alpha / 2) / alpha) as u8;
+        Color::from_rgba(mix(r, ur), mix(g, ug), mix(b, ub), alpha as u8)
+    }
+
+    /// Color from a hue in degrees, and saturation and lightness from 0 to 1.
+    pub fn from_hsl(hue: f32, saturation: f32, lightness: f32) -> Self {
+        let (r, g, b) = hsl_to_rgb(hue.rem_euclid(360.0), saturation.clamp(0.0, 1.0), lightness.clamp(0.0, 1.0));
+        Color::Rgb(r, g, b)
+    }
+
+    /// Hue in degrees, saturation and lightness from 0 to 1.
+    pub fn to_hsl(&self) -> Option<(f32, f32, f32)> {
+        self.rgb().map(rgb_to_hsl)
+    }
+
+    /// CIELAB lightness and the a and b axes, for comparing colors the way
+    /// the eye does.
+    pub fn to_lab(&self) -> Option<(f32, f32, f32)> {
+        self.rgb().map(lab)
+    }
+
+    // The following derive new exact colors and keep the alpha. Palette
+    // colors are taken with their default components, `Reset` stays as it is.
+
+    /// Raises the HSL lightness by `amount`, a fraction from 0 to 1.
+    pub fn lighten(&self, amount: f32) -> Color {
+        self.map_hsl(|h, s, l| (h, s, l + amount))
+    }
+
+    pub fn darken(&self, amount: f32) -> Color {
+        self.lighten(-amount)
+    }
+
+    /// Raises the HSL saturation by `amount`, a negative one desaturates.
+    pub fn saturate(&self, amount: f32) -> Color {
+        self.map_hsl(|h, s, l| (h, s + amount, l))
+    }
+
+    /// Blends towards `other`, `ratio` 0 gives this color and 1 `other`.
+    pub fn mix(&self, other: &Color, ratio: f32) -> Color {
+        let ratio = ratio.clamp(0.0, 1.0);
+        let (Some((r, g, b)), Some((or, og, ob))) = (self.rgb(), other.rgb()) else {
+            return if ratio < 0.5 { *self } else { *other };
+        };
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * ratio).round() as u8;
+        Color::from_rgba(mix(r, or), mix(g, og), mix(b, ob), mix(self.alpha(), other.alpha()))
+    }
+
+    pub fn invert(&self) -> Color {
+        match self.rgb() {
+            Some((r, g, b)) => Color::from_rgba(255 - r, 255 - g, 255 - b, self.alpha()),
+            None => *self,
+        }
+    }
+
+    /// Relative luminance as WCAG 2 defines it, 0 for black and 1 for white.
+    pub fn luminance(&self) -> Option<f32> {
+        let (r, g, b) = self.rgb()?;
+        Some(0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b))
+    }
+
+    /// WCAG 2 contrast ratio with `other`, from 1 to 21. Normal text needs
+    /// 4.5 to be readable (level AA).
+    pub fn contrast_ratio(&self, other: &Color) -> Option<f32> {
+        let (a, b) = (self.luminance()?, other.luminance()?);
+        Some((a.max(b) + 0.05) / (a.min(b) + 0.05))
+    }
+
+    /// Black or white, whichever contrasts more with this color as
+    /// background. Over `Reset` it is `Reset`, the terminal's own choice.
+    pub fn contrast_text(&self) -> Color {
+        let (black, white) = (Color::Rgb(0, 0, 0), Color::Rgb(255, 255, 255));
+        match (self.contrast_ratio(&black), self.contrast_ratio(&white)) {
+            (Some(on_black), Some(on_white)) if on_black >= on_white => black,
+            (Some(_), Some(_)) => white,
+            _ => Color::Reset,
+        }
+    }
+
+    fn map_hsl(&self, change: impl Fn(f32, f32, f32) -> (f32, f32, f32)) -> Color {
+        let Some((h, s, l)) = self.to_hsl() else {
+            return *self;
+        };
+        let (h, s, l) = change(h, s, l);
+        let (r, g, b) = hsl_to_rgb(h, s.clamp(0.0, 1.0), l.clamp(0.0, 1.0));
+        Color::from_rgba(r, g, b, self.alpha())
+    }
+
+    /// The color a terminal with `depth` can show which looks closest to
+    /// this one, `None` if it shows no colors. Exact colors map to the
+    /// palette by their distance in CIELAB.
+    pub fn downgrade(&self, depth: ColorDepth) -> Option<Color> {
+        match (depth, *self) {
+            (ColorDepth::NoColor, _) => None,
+            (ColorDepth::TrueColor, color) => Some(color),
+            (ColorDepth::Indexed256, Color::Rgb(r, g, b) | Color::Rgba(r, g, b, _)) => {
+                Some(Color::Indexed(nearest((r, g, b), 16..256)))
+            }
+            (ColorDepth::Ansi16, Color::Indexed(index @ 0..=15)) => Some(Color::ansi(index)),
+            (ColorDepth::Ansi16, color @ (Color::Indexed(_) | Color::Rgb(..) | Color::Rgba(..))) => {
+                color.rgb().map(|rgb| Color::ansi(nearest(rgb, 0..16)))
+            }
+            // named colors and `Reset` are there at every depth
+            (_, color) => Some(color),
+        }
+    }
+
+    // Position among the 16 named colors.
+    fn ansi_index(&self) -> Option<u8> {
+        NAMED.iter().position(|named| named == self).map(|index| index as u8)
+    }
+
+    // SGR parameters selecting the color as foreground (`base` 30) or
+    // background (`base` 40).
+    fn params(&self, base: u8) -> String {
+        match *self {
+            Color::Reset => (base + 9).to_string(),
+            Color::Indexed(index) => format!("{};5;{}", base + 8, index),
+            Color::Rgb(r, g, b) | Color::Rgba(r, g, b, _) => format!("{};2;{};{};{}", base + 8, r, g, b),
+            named => match named.ansi_index().unwrap_or(0) {
+                index @ 0..=7 => (base + index).to_string(),
+                index => (base + 60 + index - 8).to_string(),
+            },
+        }
     }
 
     pub fn to_front_seq(&self) -> String {
-        return "\x1b[38;".to_string() + &self.code;
+        format!("\x1b[{}m", self.params(30))
     }
 
     pub fn to_back_seq(&self) -> String {
-        return "\x1b[48;".to_string() + &self.code;
+        format!("\x1b[{}m", self.params(40))
+    }
+}
+
+/// Reads colors as theme files write them:
+///
+/// - hex as `#rgb`, `#rrggbb` or `#rrggbbaa`
+/// - `rgb(30, 30, 46)`, `rgb(30 30 46 / 50%)` and `rgba(...)`
+/// - `hsl(220, 40%, 20%)` and `hsla(...)`
+/// - palette indexes such as `196`
+/// - `reset` or `default`, the named ANSI colors such as `red` or
+///   `bright-blue`, and the CSS color names such as `rebeccapurple`
+///
+/// Names ignore case, `-`, `_` and spaces. Where the ANSI and CSS names
+/// overlap, like `red`, the ANSI color from the terminal's theme is meant.
+impl FromStr for Color {
+    type Err = ParseColorError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let error = |reason: String| ParseColorError { input: text.to_string(), reason };
+        let lower = text.trim().to_ascii_lowercase();
+        if lower.is_empty() {
+            return Err(error("the text is empty".to_string()));
+        }
+        if let Some(digits) = lower.strip_prefix('#') {
+            return parse_hex(digits).map_err(error);
+        }
+        if let Some((function, args)) = lower.split_once('(') {
+            let args = args.strip_suffix(')').ok_or_else(|| error("')' is missing at the end".to_string()))?;
+            return match function.trim_end() {
+                "rgb" | "rgba" => parse_rgb(args),
+                "hsl" | "hsla" => parse_hsl(args),
+                function => Err(format!("{}() is no color function, expected rgb() or hsl()", function)),
+            }.map_err(error);
+        }
+        if lower.bytes().all(|byte| byte.is_ascii_digit()) {
+            return lower.parse().map(Color::Indexed)
+                .map_err(|_| error("palette indexes go from 0 to 255".to_string()));
+        }
+
+        let name: String = lower.chars().filter(|c| !matches!(c, '-' | '_' | ' ')).collect();
+        if matches!(name.as_str(), "reset" | "default") {
+            return Ok(Color::Reset);
+        }
+        let ansi = |name: &str| ANSI_NAMES.iter().position(|ansi| *ansi == name);
+        if let Some(index) = ansi(&name) {
+            return Ok(NAMED[index]);
+        }
+        if let Some(index) = name.strip_prefix("bright").and_then(ansi) {
+            return Ok(NAMED[index + 8]);
+        }
+        if name == "transparent" {
+            return Ok(Color::Rgba(0, 0, 0, 0));
+        }
+        CSS_COLORS.binary_search_by_key(&name.as_str(), |(css, _)| css)
+            .map(|idx| {
+                let [_, r, g, b] = CSS_COLORS[idx].1.to_be_bytes();
+                Color::Rgb(r, g, b)
+            })
+            .map_err(|_| error("no ANSI or CSS color has this name".to_string()))
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+/// Why a text is no color, returned by `Color::from_str`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseColorError {
+    input: String,
+    reason: String,
+}
+
+impl ParseColorError {
+    /// The text which failed to parse.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+}
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid color {:?}: {}", self.input, self.reason)
+    }
+}
+
+impl Error for ParseColorError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum EffectType {
     Reset,
     Bold,
@@ -42,68 +385,662 @@ pub enum EffectType {
     Hide,
     Cancel,
     FrontColor,
-    BackColor
+    BackColor,
+    /// Makes the cells an OSC 8 hyperlink, or ends the link.
+    Link,
 }
 
-#[derive(Clone)]
+// attribute effects in the order of their `Modifier` flags
+const ATTRIBUTES: [EffectType; 9] = [
+    EffectType::Bold, EffectType::Thin, EffectType::Italic, EffectType::UnderLine, EffectType::Bling,
+    EffectType::FastBling, EffectType::Invert, EffectType::Hide, EffectType::Cancel,
+];
+
+impl EffectType {
+    // SGR parameter of the attribute, colors carry their own sequence.
+    fn sgr_code(&self) -> Option<u8> {
+        match self {
+            EffectType::Reset => Some(0),
+            EffectType::Bold => Some(1),
+            EffectType::Thin => Some(2),
+            EffectType::Italic => Some(3),
+            EffectType::UnderLine => Some(4),
+            EffectType::Bling => Some(5),
+            EffectType::FastBling => Some(6),
+            EffectType::Invert => Some(7),
+            EffectType::Hide => Some(8),
+            EffectType::Cancel => Some(9),
+            EffectType::FrontColor | EffectType::BackColor | EffectType::Link => None,
+        }
+    }
+
+    // Flag of the attribute, colors, links and resets have none.
+    fn modifier(&self) -> Option<Modifier> {
+        ATTRIBUTES.iter().position(|kind| kind == self).map(|idx| Modifier(1 << idx))
+    }
+}
+
+/// Set of text attributes, combined with `|` as in
+/// `Modifier::BOLD | Modifier::ITALIC`.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Modifier(u16);
+
+impl Modifier {
+    pub const BOLD: Modifier = Modifier(1 << 0);
+    pub const DIM: Modifier = Modifier(1 << 1);
+    pub const ITALIC: Modifier = Modifier(1 << 2);
+    pub const UNDERLINE: Modifier = Modifier(1 << 3);
+    pub const BLINK: Modifier = Modifier(1 << 4);
+    pub const RAPID_BLINK: Modifier = Modifier(1 << 5);
+    pub const REVERSE: Modifier = Modifier(1 << 6);
+    pub const HIDDEN: Modifier = Modifier(1 << 7);
+    pub const STRIKETHROUGH: Modifier = Modifier(1 << 8);
+
+    const NAMES: [&'static str; 9] = [
+        "BOLD", "DIM", "ITALIC", "UNDERLINE", "BLINK", "RAPID_BLINK", "REVERSE", "HIDDEN", "STRIKETHROUGH",
+    ];
+    // SGR parameters switching each flag off, some flags share one
+    const OFF_CODES: [u8; 9] = [22, 22, 23, 24, 25, 25, 27, 28, 29];
+
+    pub const fn empty() -> Self {
+        Modifier(0)
+    }
+
+    pub const fn all() -> Self {
+        Modifier((1 << Modifier::NAMES.len()) - 1)
+    }
+
+    pub const fn bits(&self) -> u16 {
+        self.0
+    }
+
+    /// The set with these bits, `None` if a bit is no known flag.
+    pub const fn from_bits(bits: u16) -> Option<Self> {
+        if bits & !Modifier::all().0 == 0 {
+            Some(Modifier(bits))
+        } else {
+            None
+        }
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether all flags of `other` are set.
+    pub const fn contains(&self, other: Modifier) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn intersects(&self, other: Modifier) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub fn insert(&mut self, other: Modifier) {
+        self.0 |= other.0;
+    }
+
+    pub fn remove(&mut self, other: Modifier) {
+        self.0 &= !other.0;
+    }
+
+    /// The single flags of the set, in the order of their SGR codes.
+    pub fn iter(&self) -> impl Iterator<Item = Modifier> {
+        let bits = self.0;
+        (0..Modifier::NAMES.len()).map(|idx| Modifier(1 << idx)).filter(move |flag| bits & flag.0 != 0)
+    }
+
+    // Position of a single flag in the tables.
+    fn index(&self) -> usize {
+        self.0.trailing_zeros() as usize
+    }
+
+    fn sgr_code(&self) -> u8 {
+        self.index() as u8 + 1
+    }
+
+    fn off_code(&self) -> u8 {
+        Modifier::OFF_CODES[self.index()]
+    }
+}
+
+impl fmt::Debug for Modifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("(empty)");
+        }
+        let names: Vec<_> = self.iter().map(|flag| Modifier::NAMES[flag.index()]).collect();
+        f.write_str(&names.join(" | "))
+    }
+}
+
+impl BitOr for Modifier {
+    type Output = Modifier;
+
+    fn bitor(self, other: Modifier) -> Modifier {
+        Modifier(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for Modifier {
+    fn bitor_assign(&mut self, other: Modifier) {
+        self.insert(other);
+    }
+}
+
+impl BitAnd for Modifier {
+    type Output = Modifier;
+
+    fn bitand(self, other: Modifier) -> Modifier {
+        Modifier(self.0 & other.0)
+    }
+}
+
+/// The flags of the left set which the right one lacks.
+impl Sub for Modifier {
+    type Output = Modifier;
+
+    fn sub(self, other: Modifier) -> Modifier {
+        Modifier(self.0 & !other.0)
+    }
+}
+
+impl Not for Modifier {
+    type Output = Modifier;
+
+    fn not(self) -> Modifier {
+        Modifier(!self.0 & Modifier::all().0)
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Effect {
     pub kind: EffectType,
     pub pos: usize,
-    seq: String
+    color: Option<Color>,
+    url: Option<String>,
 }
 
 impl Effect {
+    fn attribute(kind: EffectType, pos: usize) -> Self {
+        Effect { kind, pos, color: None, url: None }
+    }
+
     pub fn reset(pos: usize) -> Self {
-        Effect { kind: EffectType::Reset, pos: pos, seq: "\x1b[0m".to_string()}
+        Effect::attribute(EffectType::Reset, pos)
     }
 
     pub fn bold(pos: usize) -> Self {
-        Effect { kind: EffectType::Bold, pos: pos, seq: "\x1b[1m".to_string() }
+        Effect::attribute(EffectType::Bold, pos)
     }
 
     pub fn thin(pos: usize) -> Effect {
-        Effect { kind: EffectType::Thin, pos: pos, seq: "\x1b[2m".to_string() }
+        Effect::attribute(EffectType::Thin, pos)
     }
 
     pub fn italic(pos: usize) -> Effect {
-        Effect { kind: EffectType::Italic, pos: pos, seq: "\x1b[3m".to_string() }
+        Effect::attribute(EffectType::Italic, pos)
     }
 
     pub fn under_line(pos: usize) -> Effect {
-        Effect { kind: EffectType::UnderLine, pos: pos, seq: "\x1b[4m".to_string() }
+        Effect::attribute(EffectType::UnderLine, pos)
     }
 
     pub fn bling(pos: usize) -> Effect {
-        Effect { kind: EffectType::Bling, pos: pos, seq: "\x1b[5m".to_string() }
+        Effect::attribute(EffectType::Bling, pos)
     }
 
     pub fn fast_bling(pos: usize) -> Effect {
-        Effect { kind: EffectType::FastBling, pos: pos, seq: "\x1b[6m".to_string() }
+        Effect::attribute(EffectType::FastBling, pos)
     }
 
     pub fn invert(pos: usize) -> Effect {
-        Effect { kind: EffectType::Invert, pos: pos, seq: "\x1b[7m".to_string() }
+        Effect::attribute(EffectType::Invert, pos)
     }
 
     pub fn hide(pos: usize) -> Effect {
-        Effect { kind: EffectType::Hide, pos: pos, seq: "\x1b[8m".to_string() }
+        Effect::attribute(EffectType::Hide, pos)
     }
 
     pub fn cancel(pos: usize) -> Effect {
-        Effect { kind: EffectType::Cancel, pos: pos, seq: "\x1b[9m".to_string() }
+        Effect::attribute(EffectType::Cancel, pos)
     }
 
     pub fn front_color(color: Color, pos: usize) -> Self {
-        Effect { kind: EffectType::FrontColor, pos: pos, seq: color.to_front_seq() }
+        Effect { kind: EffectType::FrontColor, pos, color: Some(color), url: None }
     }
 
     pub fn back_color(color: Color, pos: usize) -> Self {
-        Effect { kind: EffectType::BackColor, pos: pos, seq: color.to_back_seq() }
+        Effect { kind: EffectType::BackColor, pos, color: Some(color), url: None }
+    }
+
+    /// Links the following cells to `url`. Control characters are dropped
+    /// from it and other characters beyond ASCII are percent-encoded, so the
+    /// url can't end the OSC 8 sequence it is written in.
+    pub fn link(url: &str, pos: usize) -> Self {
+        Effect { kind: EffectType::Link, pos, color: None, url: Some(clean_url(url)).filter(|url| !url.is_empty()) }
+    }
+
+    pub fn end_link(pos: usize) -> Self {
+        Effect::attribute(EffectType::Link, pos)
     }
 }
 
-impl ToString for Effect {
-    fn to_string(&self) -> String {
-        return self.seq.clone();
+impl fmt::Display for Effect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.color, self.kind) {
+            (Some(color), EffectType::FrontColor) => f.write_str(&color.to_front_seq()),
+            (Some(color), EffectType::BackColor) => f.write_str(&color.to_back_seq()),
+            (_, EffectType::Link) => f.write_str(&link_seq(&self.url)),
+            _ => write!(f, "\x1b[{}m", self.kind.sgr_code().unwrap_or(0)),
+        }
     }
-}
\ No newline at end of file
+}
+
+/// Look of a single cell, such as the result of applying a run of
+/// `Effect`s. Escape sequences are only made from it when it is written out.
+///
+/// Unset colors are the terminal defaults. As a patch laid over another
+/// style with `patch`, unset colors keep the ones below instead, and
+/// `sub_modifier` lists attributes to switch off.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Style {
+    pub front: Option<Color>,
+    pub back: Option<Color>,
+    /// Attributes the style switches on.
+    pub add_modifier: Modifier,
+    /// Attributes the style switches off when patched over another one.
+    pub sub_modifier: Modifier,
+    /// Target of the hyperlink the cells belong to.
+    pub link: Option<String>,
+}
+
+impl Style {
+    pub fn with_front(mut self, color: Color) -> Self {
+        self.front = Some(color);
+        self
+    }
+
+    pub fn with_back(mut self, color: Color) -> Self {
+        self.back = Some(color);
+        self
+    }
+
+    pub fn add_modifier(mut self, modifier: Modifier) -> Self {
+        self.sub_modifier.remove(modifier);
+        self.add_modifier.insert(modifier);
+        self
+    }
+
+    pub fn remove_modifier(mut self, modifier: Modifier) -> Self {
+        self.add_modifier.remove(modifier);
+        self.sub_modifier.insert(modifier);
+        self
+    }
+
+    pub fn has_modifier(&self, modifier: Modifier) -> bool {
+        self.add_modifier.contains(modifier)
+    }
+
+    /// `other` laid over this style: its colors and link win where it has
+    /// them, and its modifiers are added to or removed from these.
+    pub fn patch(mut self, other: &Style) -> Style {
+        self.front = other.front.or(self.front);
+        self.back = other.back.or(self.back);
+        self.add_modifier = (self.add_modifier - other.sub_modifier) | other.add_modifier;
+        self.sub_modifier = (self.sub_modifier - other.add_modifier) | other.sub_modifier;
+        self.link = other.link.clone().or(self.link);
+        self
+    }
+
+    pub fn apply(&mut self, effect: &Effect) {
+        match effect.kind {
+            EffectType::Reset => *self = Style::default(),
+            EffectType::FrontColor => self.front = effect.color,
+            EffectType::BackColor => self.back = effect.color,
+            EffectType::Link => self.link = effect.url.clone(),
+            kind => {
+                if let Some(modifier) = kind.modifier() {
+                    self.add_modifier.insert(modifier);
+                    self.sub_modifier.remove(modifier);
+                }
+            }
+        }
+    }
+
+    /// This style drawn over `under`: translucent colors are blended with
+    /// the colors below, the foreground with the resulting background.
+    pub fn blend_over(&self, under: &Style) -> Style {
+        let mut style = self.clone();
+        style.back = blend_color(&self.back, &under.back);
+        style.front = blend_color(&self.front, &style.back);
+        style
+    }
+
+    /// This style seen through a translucent `color`, as under a backdrop.
+    pub fn tint(&self, color: &Color) -> Style {
+        let tint = Some(*color);
+        let mut style = self.clone();
+        style.front = self.front.as_ref().map(|front| color.blend_over(front));
+        style.back = blend_color(&tint, &self.back);
+        style
+    }
+
+    /// This style as a terminal with `depth` can show it.
+    pub fn downgrade(&self, depth: ColorDepth) -> Style {
+        Style {
+            front: self.front.and_then(|color| color.downgrade(depth)),
+            back: self.back.and_then(|color| color.downgrade(depth)),
+            ..self.clone()
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == Style::default()
+    }
+
+    /// Effects at `pos` which reset the style and then select this one.
+    pub fn to_effects(&self, pos: usize) -> Vec<Effect> {
+        let mut effects = vec![Effect::reset(pos)];
+        for flag in self.add_modifier.iter() {
+            effects.push(Effect::attribute(ATTRIBUTES[flag.index()], pos));
+        }
+        if let Some(front) = &self.front {
+            effects.push(Effect::front_color(*front, pos));
+        }
+        if let Some(back) = &self.back {
+            effects.push(Effect::back_color(*back, pos));
+        }
+        if let Some(url) = &self.link {
+            effects.push(Effect::link(url, pos));
+        }
+        effects
+    }
+
+    /// Escape sequence which resets the terminal and then selects this style.
+    pub fn to_seq(&self) -> String {
+        self.to_effects(0).iter().map(|effect| effect.to_string()).collect()
+    }
+
+    /// Shortest escape sequence which changes the terminal from `from` to
+    /// this style. Only what differs is switched, using targeted codes such
+    /// as 22 or 39, unless starting over with a reset is shorter.
+    pub fn transition(&self, from: &Style) -> String {
+        if self == from {
+            return String::new();
+        }
+        // a reset leaves links alone, they are opened and closed by OSC 8
+        let link = if self.link != from.link { link_seq(&self.link) } else { String::new() };
+        // removed modifiers only matter when patching, and to the terminal
+        // an explicit `Reset` is the same as an unset color
+        let terminal = |style: &Style| Style {
+            front: style.front.filter(|color| *color != Color::Reset),
+            back: style.back.filter(|color| *color != Color::Reset),
+            link: None,
+            sub_modifier: Modifier::empty(),
+            ..style.clone()
+        };
+        let (sgr_from, sgr_to) = (terminal(from), terminal(self));
+        if sgr_to == sgr_from {
+            return link;
+        }
+
+        let (from, to) = (&sgr_from, &sgr_to);
+        let mut params = vec![];
+        let mut off_codes = vec![];
+        for flag in (from.add_modifier - to.add_modifier).iter() {
+            if !off_codes.contains(&flag.off_code()) {
+                off_codes.push(flag.off_code());
+                params.push(flag.off_code().to_string());
+            }
+        }
+        // bold and dim share their off code, a kept one is switched on again
+        for flag in to.add_modifier.iter() {
+            if !from.add_modifier.contains(flag) || off_codes.contains(&flag.off_code()) {
+                params.push(flag.sgr_code().to_string());
+            }
+        }
+        if to.front != from.front {
+            params.push(to.front.unwrap_or(Color::Reset).params(30));
+        }
+        if to.back != from.back {
+            params.push(to.back.unwrap_or(Color::Reset).params(40));
+        }
+
+        let targeted = sgr(&params);
+        let reset = sgr(&to.params());
+        if reset.len() < targeted.len() {
+            reset + &link
+        } else {
+            targeted + &link
+        }
+    }
+
+    // SGR parameters selecting this style after a reset.
+    fn params(&self) -> Vec<String> {
+        let mut params = vec!["0".to_string()];
+        params.extend(self.add_modifier.iter().map(|flag| flag.sgr_code().to_string()));
+        if let Some(front) = &self.front {
+            params.push(front.params(30));
+        }
+        if let Some(back) = &self.back {
+            params.push(back.params(40));
+        }
+        params
+    }
+}
+
+// OSC 8 sequence opening a link to `url`, or closing the open one.
+fn link_seq(url: &Option<String>) -> String {
+    format!("\x1b]8;;{}\x1b\\", clean_url(url.as_deref().unwrap_or("")))
+}
+
+// `url` with nothing but printable ASCII left in it.
+fn clean_url(url: &str) -> String {
+    let mut clean = String::new();
+    for ch in url.chars().filter(|ch| !ch.is_control()) {
+        if ch.is_ascii() {
+            clean.push(ch);
+        } else {
+            for byte in ch.to_string().bytes() {
+                clean += &format!("%{:02X}", byte);
+            }
+        }
+    }
+    clean
+}
+
+fn sgr(params: &[String]) -> String {
+    format!("\x1b[{}m", params.join(";"))
+}
+
+// Palette entry in `range` closest to `rgb`.
+fn nearest(rgb: (u8, u8, u8), range: std::ops::Range<usize>) -> u8 {
+    static PALETTE: OnceLock<Vec<(f32, f32, f32)>> = OnceLock::new();
+    let palette = PALETTE.get_or_init(|| {
+        (0..=255).map(|index| lab(Color::Indexed(index).rgb().unwrap_or_default())).collect()
+    });
+    let (l, a, b) = lab(rgb);
+    let distance = |&(pl, pa, pb): &(f32, f32, f32)| (l - pl).powi(2) + (a - pa).powi(2) + (b - pb).powi(2);
+    range.min_by(|x, y| distance(&palette[*x]).total_cmp(&distance(&palette[*y]))).unwrap_or(0) as u8
+}
+
+// The part of `Color::from_str` after the '#'.
+fn parse_hex(digits: &str) -> Result<Color, String> {
+    if let Some(bad) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(format!("'{}' is no hex digit", bad));
+    }
+    let value = u32::from_str_radix(digits, 16).unwrap_or(0);
+    let byte = |shift: u32| (value >> shift) as u8;
+    // each digit of the short form stands for two
+    let short = |shift: u32| (value >> shift & 0xf) as u8 * 0x11;
+    match digits.len() {
+        3 => Ok(Color::Rgb(short(8), short(4), short(0))),
+        6 => Ok(Color::Rgb(byte(16), byte(8), byte(0))),
+        8 => Ok(Color::from_rgba(byte(24), byte(16), byte(8), byte(0))),
+        len => Err(format!("expected 3, 6 or 8 hex digits, found {}", len)),
+    }
+}
+
+fn parse_rgb(args: &str) -> Result<Color, String> {
+    let ([r, g, b], alpha) = components(args)?;
+    let channel = |text: &str| match text.strip_suffix('%') {
+        Some(percent) => ranged(percent, 0.0, 100.0).map(|p| p * 2.55),
+        None => ranged(text, 0.0, 255.0),
+    };
+    let (r, g, b) = (channel(r)?, channel(g)?, channel(b)?);
+    Ok(Color::from_rgba(r.round() as u8, g.round() as u8, b.round() as u8, alpha.map_or(Ok(255), parse_alpha)?))
+}
+
+fn parse_hsl(args: &str) -> Result<Color, String> {
+    let ([hue, saturation, lightness], alpha) = components(args)?;
+    let hue = number(hue.strip_suffix("deg").unwrap_or(hue))?.rem_euclid(360.0);
+    let percent = |text: &str| ranged(text.strip_suffix('%').unwrap_or(text), 0.0, 100.0).map(|p| p / 100.0);
+    let (r, g, b) = hsl_to_rgb(hue, percent(saturation)?, percent(lightness)?);
+    Ok(Color::from_rgba(r, g, b, alpha.map_or(Ok(255), parse_alpha)?))
+}
+
+// The three components of `rgb()` or `hsl()`, separated by commas or
+// spaces, and the alpha as fourth one or after a '/'.
+fn components(args: &str) -> Result<([&str; 3], Option<&str>), String> {
+    let (main, mut alpha) = match args.split_once('/') {
+        Some((main, alpha)) => (main, Some(alpha.trim())),
+        None => (args, None),
+    };
+    let mut parts: Vec<_> = main.split(|c: char| c == ',' || c.is_whitespace()).filter(|part| !part.is_empty()).collect();
+    if alpha.is_none() && parts.len() == 4 {
+        alpha = parts.pop();
+    }
+    match parts[..] {
+        [a, b, c] => Ok(([a, b, c], alpha)),
+        _ => Err(format!("expected 3 components, found {}", parts.len())),
+    }
+}
+
+// Alpha as fraction from 0 to 1 or as percentage.
+fn parse_alpha(text: &str) -> Result<u8, String> {
+    let alpha = match text.strip_suffix('%') {
+        Some(percent) => ranged(percent, 0.0, 100.0)? / 100.0,
+        None => ranged(text, 0.0, 1.0)?,
+    };
+    Ok((alpha * 255.0).round() as u8)
+}
+
+fn number(text: &str) -> Result<f32, String> {
+    text.parse::<f32>().ok().filter(|value| value.is_finite()).ok_or_else(|| format!("'{}' is no number", text))
+}
+
+fn ranged(text: &str, min: f32, max: f32) -> Result<f32, String> {
+    let value = number(text)?;
+    if (min..=max).contains(&value) {
+        Ok(value)
+    } else {
+        Err(format!("{} is outside of {} to {}", text, min, max))
+    }
+}
+
+// sRGB components of a hue in degrees with saturation and lightness from
+// 0 to 1.
+fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> (u8, u8, u8) {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let sector = hue / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let base = lightness - chroma / 2.0;
+    let channel = |c: f32| ((c + base) * 255.0).round() as u8;
+    (channel(r), channel(g), channel(b))
+}
+
+fn rgb_to_hsl((r, g, b): (u8, u8, u8)) -> (f32, f32, f32) {
+    let (r, g, b) = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+    let lightness = (max + min) / 2.0;
+    let chroma = max - min;
+    if chroma == 0.0 {
+        return (0.0, 0.0, lightness);
+    }
+    let saturation = chroma / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == r {
+        (g - b) / chroma
+    } else if max == g {
+        (b - r) / chroma + 2.0
+    } else {
+        (r - g) / chroma + 4.0
+    };
+    ((hue * 60.0).rem_euclid(360.0), saturation, lightness)
+}
+
+// Linear light of an sRGB channel.
+fn linear(channel: u8) -> f32 {
+    let c = channel as f32 / 255.0;
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+// CIELAB coordinates of an sRGB color under the D65 white point.
+fn lab((r, g, b): (u8, u8, u8)) -> (f32, f32, f32) {
+    let (r, g, b) = (linear(r), linear(g), linear(b));
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+    let f = |t: f32| if t > 0.008856 { t.cbrt() } else { 7.787 * t + 16.0 / 116.0 };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    (116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+}
+
+fn blend_color(top: &Option<Color>, under: &Option<Color>) -> Option<Color> {
+    match (top, under) {
+        (Some(top), Some(under)) => Some(top.blend_over(under)),
+        (top, _) => *top,
+    }
+}
+
+// names of the first 8 palette entries, the next 8 add "bright"
+const ANSI_NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+// the CSS named colors, sorted by name
+const CSS_COLORS: &[(&str, u32)] = &[
+    ("aliceblue", 0xf0f8ff), ("antiquewhite", 0xfaebd7), ("aqua", 0x00ffff), ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff), ("beige", 0xf5f5dc), ("bisque", 0xffe4c4), ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd), ("blue", 0x0000ff), ("blueviolet", 0x8a2be2), ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887), ("cadetblue", 0x5f9ea0), ("chartreuse", 0x7fff00), ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50), ("cornflowerblue", 0x6495ed), ("cornsilk", 0xfff8dc), ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff), ("darkblue", 0x00008b), ("darkcyan", 0x008b8b), ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9), ("darkgreen", 0x006400), ("darkgrey", 0xa9a9a9), ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b), ("darkolivegreen", 0x556b2f), ("darkorange", 0xff8c00), ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000), ("darksalmon", 0xe9967a), ("darkseagreen", 0x8fbc8f), ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f), ("darkslategrey", 0x2f4f4f), ("darkturquoise", 0x00ced1), ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493), ("deepskyblue", 0x00bfff), ("dimgray", 0x696969), ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff), ("firebrick", 0xb22222), ("floralwhite", 0xfffaf0), ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff), ("gainsboro", 0xdcdcdc), ("ghostwhite", 0xf8f8ff), ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520), ("gray", 0x808080), ("green", 0x008000), ("greenyellow", 0xadff2f),
+    ("grey", 0x808080), ("honeydew", 0xf0fff0), ("hotpink", 0xff69b4), ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082), ("ivory", 0xfffff0), ("khaki", 0xf0e68c), ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5), ("lawngreen", 0x7cfc00), ("lemonchiffon", 0xfffacd), ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080), ("lightcyan", 0xe0ffff), ("lightgoldenrodyellow", 0xfafad2), ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90), ("lightgrey", 0xd3d3d3), ("lightpink", 0xffb6c1), ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa), ("lightskyblue", 0x87cefa), ("lightslategray", 0x778899), ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de), ("lightyellow", 0xffffe0), ("lime", 0x00ff00), ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6), ("magenta", 0xff00ff), ("maroon", 0x800000), ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd), ("mediumorchid", 0xba55d3), ("mediumpurple", 0x9370db), ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee), ("mediumspringgreen", 0x00fa9a), ("mediumturquoise", 0x48d1cc), ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970), ("mintcream", 0xf5fffa), ("mistyrose", 0xffe4e1), ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead), ("navy", 0x000080), ("oldlace", 0xfdf5e6), ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23), ("orange", 0xffa500), ("orangered", 0xff4500), ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa), ("palegreen", 0x98fb98), ("paleturquoise", 0xafeeee), ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5), ("peachpuff", 0xffdab9), ("peru", 0xcd853f), ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd), ("powderblue", 0xb0e0e6), ("purple", 0x800080), ("rebeccapurple", 0x663399),
+    ("red", 0xff0000), ("rosybrown", 0xbc8f8f), ("royalblue", 0x4169e1), ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072), ("sandybrown", 0xf4a460), ("seagreen", 0x2e8b57), ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d), ("silver", 0xc0c0c0), ("skyblue", 0x87ceeb), ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090), ("slategrey", 0x708090), ("snow", 0xfffafa), ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4), ("tan", 0xd2b48c), ("teal", 0x008080), ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347), ("turquoise", 0x40e0d0), ("violet", 0xee82ee), ("wheat", 0xf5deb3),
+    ("white", 0xffffff), ("whitesmoke", 0xf5f5f5), ("yellow", 0xffff00), ("yellowgreen", 0x9acd32),
+];
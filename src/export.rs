@@ -0,0 +1,221 @@
+use crate::buffer::{Buffer, Cell};
+use crate::effect::{Modifier, Style};
+
+// colors used where a style leaves the terminal default
+const DEFAULT_FRONT: (u8, u8, u8) = (229, 229, 229);
+const DEFAULT_BACK: (u8, u8, u8) = (24, 24, 24);
+
+// size of one cell in the svg, in pixels
+const CELL_WIDTH: f32 = 8.4;
+const CELL_HEIGHT: f32 = 17.0;
+const FONT_SIZE: f32 = 14.0;
+
+/// Renders the buffer as a standalone HTML document with a `<pre>` block.
+pub fn to_html(buffer: &Buffer) -> String {
+    let mut html = String::new();
+    html += "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<style>\n";
+    html += &format!(
+        "pre {{ margin: 0; padding: 8px; font-family: monospace; line-height: 1.2; color: {}; background: {}; }}\n",
+        hex(DEFAULT_FRONT), hex(DEFAULT_BACK)
+    );
+    // wide glyphs are pinned to two columns so the grid lines up
+    html += ".w { display: inline-block; width: 2ch; }\n";
+    html += "</style>\n</head>\n<body>\n<pre>";
+
+    for y in 0..buffer.height() {
+        for (style, cells) in runs(buffer.row(y)) {
+            if let Some(url) = &style.link {
+                html += &format!("<a href=\"{}\">", escape(url));
+            }
+            let css = css(style);
+            if !css.is_empty() {
+                html += &format!("<span style=\"{}\">", css);
+            }
+            for cell in cells {
+                let text = escape(&cell.symbol);
+                if cell.width > 1 {
+                    html += &format!("<span class=\"w\">{}</span>", text);
+                } else {
+                    html += &text;
+                }
+            }
+            if !css.is_empty() {
+                html += "</span>";
+            }
+            if style.link.is_some() {
+                html += "</a>";
+            }
+        }
+        html += "\n";
+    }
+
+    html += "</pre>\n</body>\n</html>\n";
+    html
+}
+
+/// Renders the buffer as an SVG image, placing every glyph on a fixed
+/// monospace grid.
+pub fn to_svg(buffer: &Buffer) -> String {
+    let width = buffer.width() as f32 * CELL_WIDTH;
+    let height = buffer.height() as f32 * CELL_HEIGHT;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+        num(width), num(height), num(width), num(height)
+    );
+    svg += &format!(
+        "<style>text {{ font-family: monospace; font-size: {}px; white-space: pre; }}</style>\n",
+        FONT_SIZE
+    );
+    svg += &format!("<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n", hex(DEFAULT_BACK));
+
+    for y in 0..buffer.height() {
+        let top = y as f32 * CELL_HEIGHT;
+        let baseline = top + CELL_HEIGHT * 0.8;
+        let mut x = 0;
+
+        for (style, cells) in runs(buffer.row(y)) {
+            let (front, back) = colors(style);
+            let columns: usize = cells.iter().map(|cell| cell.width).sum();
+
+            if back != DEFAULT_BACK {
+                svg += &format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                    num(x as f32 * CELL_WIDTH), num(top), num(columns as f32 * CELL_WIDTH), num(CELL_HEIGHT), hex(back)
+                );
+            }
+
+            // every char gets the x of its cell, marks stay on their base
+            let mut text = String::new();
+            let mut xs = vec![];
+            let mut col = x;
+            for cell in &cells {
+                if cell.symbol.trim().is_empty() || style.has_modifier(Modifier::HIDDEN) {
+                    col += cell.width;
+                    continue;
+                }
+                for ch in cell.symbol.chars() {
+                    text += &escape(&ch.to_string());
+                    xs.push(num(col as f32 * CELL_WIDTH));
+                }
+                col += cell.width;
+            }
+
+            if !text.is_empty() {
+                svg += &format!(
+                    "<text x=\"{}\" y=\"{}\" fill=\"{}\"{}>{}</text>\n",
+                    xs.join(" "), num(baseline), hex(front), svg_attributes(style), text
+                );
+            }
+            x += columns;
+        }
+    }
+
+    svg += "</svg>\n";
+    svg
+}
+
+// Groups the cells of a row into runs of the same style, skipping the
+// continuation cells of wide glyphs.
+fn runs(row: &[Cell]) -> Vec<(&Style, Vec<&Cell>)> {
+    let mut runs: Vec<(&Style, Vec<&Cell>)> = vec![];
+    for cell in row.iter().filter(|cell| cell.width > 0) {
+        match runs.last_mut() {
+            Some((style, cells)) if **style == cell.style => cells.push(cell),
+            _ => runs.push((&cell.style, vec![cell])),
+        }
+    }
+    runs
+}
+
+// Effective foreground and background after applying inversion.
+fn colors(style: &Style) -> ((u8, u8, u8), (u8, u8, u8)) {
+    let front = style.front.and_then(|c| c.rgb()).unwrap_or(DEFAULT_FRONT);
+    let back = style.back.and_then(|c| c.rgb()).unwrap_or(DEFAULT_BACK);
+    if style.has_modifier(Modifier::REVERSE) {
+        (back, front)
+    } else {
+        (front, back)
+    }
+}
+
+fn css(style: &Style) -> String {
+    if style.is_default() {
+        return String::new();
+    }
+    let (front, back) = colors(style);
+    let mut css = vec![];
+
+    if style.has_modifier(Modifier::HIDDEN) {
+        css.push(format!("color: {}", hex(back)));
+    } else if front != DEFAULT_FRONT {
+        css.push(format!("color: {}", hex(front)));
+    }
+    if back != DEFAULT_BACK {
+        css.push(format!("background: {}", hex(back)));
+    }
+    if style.has_modifier(Modifier::BOLD) {
+        css.push("font-weight: bold".to_string());
+    }
+    if style.has_modifier(Modifier::DIM) {
+        css.push("opacity: 0.6".to_string());
+    }
+    if style.has_modifier(Modifier::ITALIC) {
+        css.push("font-style: italic".to_string());
+    }
+
+    let mut lines = vec![];
+    if style.has_modifier(Modifier::UNDERLINE) {
+        lines.push("underline");
+    }
+    if style.has_modifier(Modifier::STRIKETHROUGH) {
+        lines.push("line-through");
+    }
+    if !lines.is_empty() {
+        css.push(format!("text-decoration: {}", lines.join(" ")));
+    }
+
+    css.join("; ")
+}
+
+fn svg_attributes(style: &Style) -> String {
+    let mut attributes = String::new();
+    if style.has_modifier(Modifier::BOLD) {
+        attributes += " font-weight=\"bold\"";
+    }
+    if style.has_modifier(Modifier::DIM) {
+        attributes += " opacity=\"0.6\"";
+    }
+    if style.has_modifier(Modifier::ITALIC) {
+        attributes += " font-style=\"italic\"";
+    }
+
+    let mut lines = vec![];
+    if style.has_modifier(Modifier::UNDERLINE) {
+        lines.push("underline");
+    }
+    if style.has_modifier(Modifier::STRIKETHROUGH) {
+        lines.push("line-through");
+    }
+    if !lines.is_empty() {
+        attributes += &format!(" text-decoration=\"{}\"", lines.join(" "));
+    }
+    attributes
+}
+
+// Coordinates with at most two decimals and no float noise.
+fn num(value: f32) -> String {
+    let text = format!("{:.2}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn hex((r, g, b): (u8, u8, u8)) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
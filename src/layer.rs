@@ -0,0 +1,98 @@
+use crate::buffer::{Buffer, Cell};
+
+struct Layer {
+    name: String,
+    z: i32,
+    buffer: Buffer,
+}
+
+/// Stack of named buffers drawn in z order. Transparent cells of a layer let
+/// the layers below show through, so overlays such as popups can be added and
+/// removed without redrawing what is under them.
+pub struct Layers {
+    width: usize,
+    height: usize,
+    layers: Vec<Layer>,
+}
+
+impl Layers {
+    pub fn new(width: usize, height: usize) -> Self {
+        Layers { width, height, layers: vec![] }
+    }
+
+    /// Returns the layer called `name`, adding a transparent one at `z` if
+    /// it doesn't exist yet. Layers with the same z are drawn in the order
+    /// they were added.
+    pub fn layer(&mut self, name: &str, z: i32) -> &mut Buffer {
+        let idx = match self.layers.iter().position(|layer| layer.name == name) {
+            Some(idx) => idx,
+            None => {
+                let idx = self.layers.iter().position(|layer| layer.z > z).unwrap_or(self.layers.len());
+                self.layers.insert(idx, Layer {
+                    name: name.to_string(),
+                    z,
+                    buffer: Buffer::transparent(self.width, self.height),
+                });
+                idx
+            }
+        };
+        &mut self.layers[idx].buffer
+    }
+
+    /// Resizes every layer, see `Buffer::resize`.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        for layer in &mut self.layers {
+            layer.buffer.resize(width, height);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Buffer> {
+        self.layers.iter().find(|layer| layer.name == name).map(|layer| &layer.buffer)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Buffer> {
+        self.layers.iter_mut().find(|layer| layer.name == name).map(|layer| &mut layer.buffer)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Buffer> {
+        let idx = self.layers.iter().position(|layer| layer.name == name)?;
+        Some(self.layers.remove(idx).buffer)
+    }
+
+    /// Names of the layers from the bottom to the top.
+    pub fn names(&self) -> Vec<&str> {
+        self.layers.iter().map(|layer| layer.name.as_str()).collect()
+    }
+
+    /// Flattens all layers into a single opaque buffer. Translucent colors
+    /// are blended with the cells below them, and a blank cell with a
+    /// translucent background tints the content below instead of hiding it.
+    /// Layers resized on their own are cut to the size of the stack.
+    pub fn compose(&self) -> Buffer {
+        let mut out = Buffer::new(self.width, self.height);
+        for layer in &self.layers {
+            let buffer = &layer.buffer;
+            for y in 0..buffer.height().min(self.height) {
+                for (x, cell) in buffer.row(y).iter().take(self.width).enumerate() {
+                    if cell.width == 0 || cell.transparent {
+                        continue;
+                    }
+
+                    let under = out.cell_mut(x, y);
+                    match &cell.style.back {
+                        Some(back) if !back.is_opaque() && cell.symbol == " " => {
+                            under.style = under.style.tint(back);
+                        }
+                        _ => {
+                            let style = cell.style.blend_over(&under.style);
+                            out.put_cell(x, y, &Cell { style, ..cell.clone() });
+                        }
+                    }
+                }
+            }
+        }
+        out
+    }
+}
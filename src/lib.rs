@@ -1,60 +1,72 @@
 
 #[macro_use]
 pub mod app;
+pub mod backend;
 pub mod buffer;
 pub mod widget;
 pub mod effect;
 pub mod border;
 pub mod timer;
+pub mod layer;
+pub mod export;
+pub mod ansi;
+pub mod record;
 
 #[cfg(test)]
 mod tests {
-    use std::io::{stdin, stdout, Write};
-    use std::time::{self, Duration};
-    use termion::{cursor};
-    use termion::input::TermRead;
-    use termion::raw::IntoRawMode;
-    use termion::event::{Key, Event};
+    use std::time;
     use unicode_width::{UnicodeWidthStr};
 
+    use crate::app::Window;
+    use crate::backend::memory::MemoryBackend;
+    use crate::backend;
+    use crate::backend::test::TestBackend;
     use crate::border;
-    use crate::buffer::{Buffer, Rect};
-    use crate::effect::{Effect, Color};
-    use crate::timer::Timer;
+    use crate::buffer::{Buffer, BufferView, CursorShape, Rect};
+    use crate::effect::{Effect, Color, ColorDepth, Modifier, Style};
+    use crate::layer::Layers;
+    use crate::export;
+    use crate::ansi;
+    use crate::timer;
+    use crate::record::{Player, Recorder, RecordingBackend};
     use crate::widget::layout::{Horizontal, Constraint, Alignment, Vertical};
     use crate::widget::widget::Widget;
+    use crate::widget::canvas::{Canvas, Marker};
 
     #[test]
+    #[cfg(feature = "termion")]
+    #[ignore = "needs an interactive terminal"]
     fn test() {
+        use std::io::{stdin, stdout};
+        use termion::input::TermRead;
+        use termion::raw::IntoRawMode;
+        use termion::event::{Key, Event};
+        use crate::backend::Backend;
+        use crate::backend::termion::TermionBackend;
+
         let stdin = stdin();
-        let mut stdout = stdout().into_raw_mode().unwrap();
+        let mut backend = TermionBackend::new(stdout().into_raw_mode().unwrap());
 
-        // write!(stdout, "{}", clear::All).unwrap();
-        write!(stdout, "{}[2J", 27 as char).unwrap();
-        write!(stdout, "{}", cursor::Goto(1, 1)).unwrap();
+        backend.clear().unwrap();
+        backend.goto(0, 0).unwrap();
+        backend.flush().unwrap();
 
         for event in stdin.events() {
-            match event.unwrap() {
-                Event::Key(Key::Ctrl('c')) => {
-                    return ;
-                }
-                _ => {}
+            if let Event::Key(Key::Ctrl('c')) = event.unwrap() {
+                return ;
             }
 
-            write!(stdout, "{}", termion::clear::All).unwrap();
-            // print!("\x1B[2J");
-            // std::process::Command::new("clear").status().unwrap();
-            
-            write!(stdout, "{}", cursor::Goto(1, 1)).unwrap();
-
-            let (width, height) = termion::terminal_size().unwrap();
-            for y in 1..height + 1 {
-                for x in 1..width {
-                    write!(stdout, "{}", termion::cursor::Goto(x, y)).unwrap();
-                    write!(stdout, "{}", x).unwrap();
+            backend.clear().unwrap();
+            backend.goto(0, 0).unwrap();
+
+            let (width, height) = backend.size().unwrap();
+            for y in 0..height {
+                for x in 0..width - 1 {
+                    backend.goto(x, y).unwrap();
+                    backend.print(&(x + 1).to_string()).unwrap();
                 }
             }
-            stdout.flush().unwrap();
+            backend.flush().unwrap();
         }
     }
 
@@ -94,16 +106,795 @@ mod tests {
         println!("run time : {}", end.as_micros() as f32 / 1000000.0)
     }
 
+    #[test]
+    fn overlap_test() {
+        let mut buf = Buffer::new(8, 1);
+        buf.push_rect_buffer(vec!["あいう".to_string()], vec![], Rect::new(0, 0, 8, 1));
+        buf.push_rect_buffer(vec!["x".to_string()], vec![], Rect::new(1, 0, 1, 1));
+        assert_eq!(buf.get_buffer(), vec![" xいう  ".to_string()]);
+
+        let effs = Rect::new(0, 0, 3, 1).make_color_eff_vec(None, Some(Color::gray(3)));
+        buf.push_rect_buffer(vec!["abcd".to_string()], effs, Rect::new(3, 0, 3, 1));
+        assert_eq!(buf.get_buffer(), vec![" x \x1b[48;5;235mabc\x1b[0m  ".to_string()]);
+        assert_eq!(buf.row(0)[2].symbol, " ");
+    }
+
+    #[test]
+    fn grapheme_test() {
+        let mut buf = Buffer::new(10, 1);
+        let line = "e\u{301}\t👨\u{200d}👩\u{200d}👧❤\u{fe0f}x🇯🇵".to_string();
+        buf.push_rect_buffer(vec![line], vec![], Rect::new(0, 0, 9, 1));
+
+        let row = buf.row(0);
+        let symbols: Vec<&str> = row.iter().map(|cell| cell.symbol.as_str()).collect();
+        assert_eq!(symbols, vec!["e\u{301}", "👨\u{200d}👩\u{200d}👧", "", "❤\u{fe0f}", "", "x", "🇯🇵", "", " ", " "]);
+
+        // a wide glyph cut by the right edge of the rect becomes padding
+        buf.push_rect_buffer(vec!["abあ".to_string()], vec![], Rect::new(0, 0, 3, 1));
+        assert_eq!(buf.get_buffer(), vec!["ab ❤\u{fe0f}x🇯🇵  ".to_string()]);
+    }
+
+    #[test]
+    fn clip_test() {
+        let mut buf = Buffer::new(6, 3);
+        let lines = vec!["abcdef".to_string(), "ghijkl".to_string()];
+
+        // runs past the screen and has fewer lines than its height
+        buf.push_rect_buffer(lines.clone(), vec![], Rect::new(3, 1, 10, 10));
+        assert_eq!(buf.get_buffer(), vec!["      ", "   abc", "   ghi"]);
+
+        buf.push_clip(Rect::new(1, 0, 3, 2));
+        buf.push_rect_buffer(vec!["xあyz".to_string()], vec![], Rect::new(0, 0, 6, 1));
+        buf.push_clip(Rect::new(0, 1, 100, 100));
+        assert_eq!(buf.clip_rect(), Rect::new(1, 1, 3, 1));
+        buf.push_rect_buffer(lines, vec![], Rect::new(0, 1, 100, 100));
+        buf.pop_clip();
+        buf.pop_clip();
+        assert_eq!(buf.get_buffer(), vec![" あy  ", " bcdbc", "   ghi"]);
+
+        buf.push_rect_buffer(vec!["z".to_string()], vec![], Rect::new(7, 4, 1, 1));
+        assert_eq!(buf.clip_rect(), buf.area());
+    }
+
+    #[test]
+    fn tiny_test() {
+        for (width, height) in [(0, 0), (1, 1), (2, 5), (5, 2), (7, 3)] {
+            let mut lay = Vertical::new(Constraint::percentage(100, 100, 0), Alignment::Start)
+                .set_border(Some(border::SINGLE))
+                .add(Box::from(
+                    Horizontal::new(Constraint::percentage(50, 100, 4), Alignment::Start)
+                    .set_border(Some(border::DOUBLE))
+                    .add(Box::from(
+                        Vertical::new(Constraint::constant(6), Alignment::End)
+                        .set_border(Some(border::SINGLE_ROUNDED))
+                    ))
+                ));
+            let mut window = Window::new(TestBackend::new(width, height));
+            window.draw(&mut lay).unwrap();
+        }
+    }
+
+    #[test]
+    fn layer_test() {
+        let mut layers = Layers::new(8, 3);
+        let lines = vec!["あいうえ".to_string(); 3];
+        layers.layer("main", 0).push_rect_buffer(lines, vec![], Rect::new(0, 0, 8, 3));
+        let main = layers.compose();
+
+        let popup = Rect::new(1, 0, 4, 3);
+        layers.layer("popup", 10).push_rect_buffer(popup.make_border_buf(Some(border::SINGLE)), vec![], popup);
+        layers.layer("tooltip", 5).push_rect_buffer(vec!["!".to_string()], vec![], Rect::new(6, 1, 1, 1));
+        assert_eq!(layers.names(), vec!["main", "tooltip", "popup"]);
+
+        let mut window = Window::new(TestBackend::new(8, 3));
+        window.render(&mut layers.compose()).unwrap();
+        // wide glyphs cut by the overlays are padded
+        window.backend().assert_text(&[
+            " ┌──┐ え",
+            " │  │ ! ",
+            " └──┘ え",
+        ].join("\n"));
+
+        assert!(layers.remove("popup").is_some());
+        layers.remove("tooltip");
+        assert_eq!(layers.compose().get_buffer(), main.get_buffer());
+        window.render(&mut layers.compose()).unwrap();
+        window.backend().assert_text("あいうえ\nあいうえ\nあいうえ");
+
+        // a layer grown on its own is cut to the stack
+        let wide = layers.layer("wide", 5);
+        wide.resize(12, 5);
+        wide.push_rect_buffer(vec!["x".repeat(12); 5], vec![], Rect::new(0, 0, 12, 5));
+        assert_eq!(layers.compose().get_buffer(), vec!["x".repeat(8); 3]);
+    }
+
+    #[test]
+    fn blend_test() {
+        let mut layers = Layers::new(4, 1);
+        let effs = Rect::new(0, 0, 4, 1).make_color_eff_vec(Some(Color::from_rgb(0, 0, 0)), Some(Color::from_rgb(200, 200, 200)));
+        layers.layer("main", 0).push_rect_buffer(vec!["abcd".to_string()], effs, Rect::new(0, 0, 4, 1));
+
+        // a half transparent black backdrop over the first two cells
+        let backdrop = Rect::new(0, 0, 2, 1);
+        let effs = backdrop.make_color_eff_vec(None, Some(Color::from_rgba(0, 0, 0, 128)));
+        layers.layer("backdrop", 1).push_rect_buffer(backdrop.make_border_buf(None), effs, backdrop);
+
+        // a glass panel with text on the last cell
+        let glass = Rect::new(3, 0, 1, 1);
+        let effs = glass.make_color_eff_vec(Some(Color::from_rgb(255, 0, 0)), Some(Color::from_rgba(255, 255, 255, 51)));
+        layers.layer("glass", 2).push_rect_buffer(vec!["x".to_string()], effs, glass);
+
+        let out = layers.compose();
+        let row = out.row(0);
+        assert_eq!(row.iter().map(|c| c.symbol.as_str()).collect::<String>(), "abcx");
+        assert_eq!(row[0].style.back, Some(Color::Rgb(100, 100, 100)));
+        assert_eq!(row[0].style.front, Some(Color::Rgb(0, 0, 0)));
+        assert_eq!(row[2].style.back, Some(Color::from_rgb(200, 200, 200)));
+        assert_eq!(row[3].style.back, Some(Color::from_rgb(211, 211, 211)));
+        assert_eq!(row[3].style.front, Some(Color::from_rgb(255, 0, 0)));
+
+        assert_eq!(Color::gray(10).blend_over(&Color::from_rgb(0, 0, 0)), Color::gray(10));
+        assert_eq!(Color::from_rgba(9, 9, 9, 0).blend_over(&Color::from_rgb(1, 2, 3)), Color::from_rgb(1, 2, 3));
+    }
+
+    #[test]
+    fn resize_test() {
+        let mut buf = Buffer::new(5, 2);
+        buf.push_rect_buffer(vec!["abcあ".to_string(), "fghij".to_string()], vec![], Rect::new(0, 0, 5, 2));
+
+        let mut window = Window::new(MemoryBackend::new(5, 2));
+        window.render(&mut buf).unwrap();
+        assert!(!buf.is_dirty());
+
+        buf.resize(4, 3);
+        assert!(buf.is_dirty());
+        assert_eq!(buf.get_buffer(), vec!["abc ", "fghi", "    "]);
+        buf.resize(6, 1);
+        assert_eq!(buf.get_buffer(), vec!["abc   "]);
+
+        let mut layers = Layers::new(2, 1);
+        layers.layer("popup", 1).push_rect_buffer(vec!["x".to_string()], vec![], Rect::new(0, 0, 1, 1));
+        layers.resize(3, 1);
+        assert!(layers.get("popup").unwrap().row(0)[2].transparent);
+
+        // the whole screen is sent again once the buffer is dirty
+        buf.resize(5, 2);
+        window.render(&mut buf).unwrap();
+        assert_eq!(window.render(&mut buf).unwrap(), 0);
+        window.backend_mut().take_output();
+        buf.mark_dirty();
+        assert!(window.render(&mut buf).unwrap() > 0);
+        assert!(window.backend().output().starts_with(b"\x1b[2J"));
+    }
+
+    #[test]
+    fn export_test() {
+        let mut buf = Buffer::new(6, 3);
+        let rect = Rect::new(0, 0, 6, 3);
+        buf.push_rect_buffer(rect.make_border_buf(Some(border::SINGLE)), vec![], rect);
+        let mut effs = vec![Effect::bold(0), Effect::under_line(0), Effect::front_color(Color::from_rgb(255, 0, 0), 0), Effect::reset(3)];
+        effs.insert(0, Effect::reset(0));
+        buf.push_rect_buffer(vec!["<あ>".to_string()], vec![effs], Rect::new(1, 1, 4, 1));
+
+        let html = export::to_html(&buf);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("┌────┐\n"));
+        assert!(html.contains(
+            "<span style=\"color: #ff0000; font-weight: bold; text-decoration: underline\">&lt;<span class=\"w\">あ</span></span>&gt;"
+        ));
+
+        let svg = export::to_svg(&buf);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"50.4\" height=\"51\""));
+        // the glyph after the wide one is placed two cells further
+        assert!(svg.contains("<text x=\"8.4 16.8\" y=\"30.6\" fill=\"#ff0000\" font-weight=\"bold\" text-decoration=\"underline\">&lt;あ</text>"));
+        assert!(svg.contains("<text x=\"33.6 42\" y=\"30.6\" fill=\"#e5e5e5\">&gt;│</text>"));
+        assert!(svg.contains("<text x=\"0 8.4 16.8 25.2 33.6 42\" y=\"47.6\" fill=\"#e5e5e5\">└────┘</text>"));
+    }
+
+    #[test]
+    fn record_test() {
+        let mut window = Window::new(RecordingBackend::new(MemoryBackend::new(5, 2)).unwrap());
+        let mut buf = Buffer::new(5, 2);
+        buf.push_rect_buffer(vec!["ab".to_string()], vec![], Rect::new(0, 0, 2, 1));
+        window.render(&mut buf).unwrap();
+        let effs = Rect::new(0, 0, 2, 1).make_color_eff_vec(Some(Color::from_rgb(255, 0, 0)), None);
+        buf.push_rect_buffer(vec!["\"あ\"".to_string()], effs, Rect::new(1, 1, 4, 1));
+        window.render(&mut buf).unwrap();
+
+        let cast = window.backend().recorder().to_asciicast();
+        let mut lines = cast.lines();
+        assert!(lines.next().unwrap().starts_with("{\"version\": 2, \"width\": 5, \"height\": 2, "));
+        assert_eq!(lines.count(), 2);
+
+        let player = Player::parse(&cast).unwrap();
+        assert_eq!(player.size(), (5, 2));
+        let frames = player.frames();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].1.get_buffer(), Buffer::new(5, 2).get_buffer().iter().enumerate()
+            .map(|(y, line)| if y == 0 { "ab   ".to_string() } else { line.clone() }).collect::<Vec<_>>());
+        let last = player.replay(player.duration());
+        assert_eq!(last.get_buffer(), buf.get_buffer());
+
+        // whole frames from the frame clock replay the same way
+        let timer = timer::Timer::new(time::Duration::from_millis(10));
+        let mut recorder = Recorder::new(5, 2);
+        recorder.record_frame(&timer, &buf);
+        let player = Player::parse(&recorder.to_asciicast()).unwrap();
+        assert_eq!(player.replay(player.duration()).get_buffer(), buf.get_buffer());
+
+        assert!(Player::parse("{\"version\": 1}").is_err());
+        assert!(Player::parse("{\"version\": 2, \"width\": 5, \"height\": 2}\n[0.5, \"o\"").is_err());
+        assert!(Player::parse("{\"version\": 2, \"width\": 5, \"height\": 2}\n[1e30, \"o\", \"x\"]").is_err());
+        assert!(Player::parse("{\"version\": 2, \"width\": 5, \"height\": 2}\n[-1, \"o\", \"x\"]").is_err());
+        assert!(Player::parse("{\"version\": 2, \"width\": 1e19, \"height\": 1e19}").is_err());
+        assert!(Player::parse("{\"version\": 2, \"width\": 2.5, \"height\": 2}").is_err());
+        assert!(Player::parse("{\"version\": 2, \"width\": 65535, \"height\": 65535}").is_err());
+        assert!(Player::parse("{\"version\": 2, \"width\": 1024, \"height\": 1024}").is_ok());
+        let nested = format!("{{\"version\": 2, \"width\": 5, \"height\": 2}}\n{}", "[".repeat(200_000));
+        assert_eq!(Player::parse(&nested).err().unwrap().kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn ansi_test() {
+        let output = "\x1b[1;31merror\x1b[0m: x\n\x1b[38;5;196mA\x1b[38;2;1;2;3mB\x1b[39;44mC\x1b[22;49m\n";
+        let (lines, effs) = ansi::parse(output);
+        assert_eq!(lines, vec!["error: x", "ABC"]);
+        let sgr = |effs: &Vec<Effect>| effs.iter().map(|e| format!("{}@{}", e, e.pos)).collect::<Vec<_>>().join(" ");
+        assert_eq!(sgr(&effs[0]), "\x1b[0m@0 \x1b[1m@0 \x1b[31m@0 \x1b[0m@5");
+        assert_eq!(sgr(&effs[1]), "\x1b[0m@0 \x1b[38;5;196m@0 \x1b[0m@1 \x1b[38;2;1;2;3m@1 \x1b[0m@2 \x1b[44m@2 \x1b[0m@3");
+
+        let mut buf = Buffer::new(10, 2);
+        buf.push_rect_buffer(lines, effs, Rect::new(1, 0, 9, 2));
+        let mut window = Window::new(TestBackend::new(10, 2));
+        window.render(&mut buf).unwrap();
+        window.backend().assert_text(&[" error: x ", " ABC      "].join("\n"));
+        let front = |color: Color| Style { front: Some(color), ..Style::default() };
+        let error = front(Color::Red).add_modifier(Modifier::BOLD);
+        let back = Style { back: Some(Color::Blue), ..Style::default() };
+        window.backend().assert_styles(&[".aaaaa....", ".bcd......"].join("\n"), &[
+            ('a', error), ('b', front(Color::indexed(196))), ('c', front(Color::from_rgb(1, 2, 3))), ('d', back),
+        ]);
+
+        // cursor movement stays inside the text whatever the input says
+        let (lines, _) = ansi::parse("abc\x1b[2Dx\x1b[99A\x1b[99999999999999999999999B\x08\x08\x08\x08y\tz\x1b]8;;url\x07\x1b(Bw\x1b[K");
+        assert_eq!(lines, vec!["yxc     zw"]);
+        let (lines, _) = ansi::parse("one\ntwo\x1b[1;2Hx\x1b[2;1H\x1b[2K\x1b[9999999999C!");
+        assert_eq!(lines, vec!["oxe".to_string(), " ".repeat(1024) + "!"]);
+    }
+
+    #[test]
+    fn sgr_test() {
+        let red = Color::from_rgb(255, 0, 0);
+        let style = |add_modifier: Modifier, front: Option<Color>| Style { front, add_modifier, ..Style::default() };
+        let bold_thin = style(Modifier::BOLD | Modifier::DIM, Some(red));
+
+        assert_eq!(bold_thin.transition(&bold_thin), "");
+        assert_eq!(style(Modifier::DIM, Some(red)).transition(&bold_thin), "\x1b[22;2m");
+        assert_eq!(style(Modifier::BOLD | Modifier::DIM, None).transition(&bold_thin), "\x1b[39m");
+        assert_eq!(style(Modifier::ITALIC, Some(red)).transition(&bold_thin), "\x1b[22;3m");
+        // a reset is shorter than switching everything off one by one
+        assert_eq!(Style::default().transition(&bold_thin), "\x1b[0m");
+        assert_eq!(style(Modifier::ITALIC, None).transition(&bold_thin), "\x1b[0;3m");
+
+        // nested panes no longer reset at every border
+        let mut buf = Buffer::new(6, 1);
+        let outer = Rect::new(0, 0, 6, 1);
+        buf.push_rect_buffer(vec!["      ".to_string()], outer.make_color_eff_vec(None, Some(Color::gray(3))), outer);
+        let inner = Rect::new(2, 0, 2, 1);
+        buf.push_rect_buffer(vec!["ab".to_string()], inner.make_color_eff_vec(Some(red), Some(Color::gray(3))), inner);
+        assert_eq!(buf.get_buffer(), vec!["\x1b[48;5;235m  \x1b[38;2;255;0;0mab\x1b[39m  \x1b[0m"]);
+
+        let mut window = Window::new(MemoryBackend::new(6, 1));
+        let written = window.render(&mut buf).unwrap();
+        assert_eq!(written, "\x1b[2J\x1b[1;1H".len() + buf.get_buffer()[0].len() + "\x1b[?25l".len());
+        let full = "\x1b[0m\x1b[48;5;235m\x1b[0m\x1b[38;2;255;0;0m\x1b[48;5;235m\x1b[0m\x1b[48;5;235m";
+        assert_eq!(window.saved_bytes(), full.len() - "\x1b[48;5;235m\x1b[38;2;255;0;0m\x1b[39m".len());
+    }
+
+    #[test]
+    fn damage_test() {
+        let mut buf = Buffer::new(20, 4);
+        assert_eq!(buf.damage(), &[Rect::new(0, 0, 20, 4)]);
+        buf.clear_damage();
+
+        // the columns beside a write are damaged too, glyphs there may be cut
+        buf.push_rect_buffer(vec!["12:00".to_string()], vec![], Rect::new(15, 3, 5, 1));
+        buf.push_rect_buffer(vec!["ab".to_string(), "cd".to_string()], vec![], Rect::new(0, 2, 2, 2));
+        buf.push_rect_buffer(vec!["b".to_string()], vec![], Rect::new(1, 2, 1, 1));
+        assert_eq!(buf.damage(), &[Rect::new(14, 3, 6, 1), Rect::new(0, 2, 3, 2)]);
+        assert_eq!(buf.damaged_spans(), vec![Rect::new(0, 2, 3, 1), Rect::new(0, 3, 3, 1), Rect::new(14, 3, 6, 1)]);
+        assert_eq!(buf.get_rect(Rect::new(14, 3, 6, 1)), vec![" 12:00"]);
+
+        let mut window = Window::new(MemoryBackend::new(20, 4));
+        window.render(&mut buf).unwrap();
+        assert!(buf.damage().is_empty());
+        window.backend_mut().take_output();
+
+        buf.push_rect_buffer(vec!["12:01".to_string()], vec![], Rect::new(15, 3, 5, 1));
+        window.render(&mut buf).unwrap();
+        assert_eq!(window.backend_mut().take_output(), b"\x1b[4;20H1");
+
+        // many scattered writes collapse into their bounds
+        for i in 0..40 {
+            buf.push_rect_buffer(vec!["-".to_string()], vec![], Rect::new(i % 20, i / 10, 1, 1));
+        }
+        assert_eq!(buf.damage(), &[buf.area()]);
+        buf.resize(10, 2);
+        assert_eq!(buf.damage(), &[Rect::new(0, 0, 10, 2)]);
+    }
+
+    #[test]
+    fn view_test() {
+        // writes far too much, starting at its own origin
+        struct Greedy;
+        impl Widget for Greedy {
+            fn get_constraint(&self) -> Constraint {
+                Constraint::constant(3)
+            }
+            fn get_alignment(&self) -> Alignment {
+                Alignment::Start
+            }
+            fn write_buffer(&mut self, view: &mut BufferView) {
+                assert_eq!(view.area(), Rect::new(0, 0, 3, view.height()));
+                view.push_rect_buffer(vec!["#".repeat(10); 10], vec![], Rect::new(0, 0, 10, 10));
+            }
+        }
+
+        let mut lay = Horizontal::new(Constraint::percentage(100, 100, 0), Alignment::Start)
+            .set_border(Some(border::SINGLE))
+            .add(Box::new(Greedy))
+            .add(Box::new(Greedy));
+        let mut window = Window::new(TestBackend::new(9, 4));
+        window.draw(&mut lay).unwrap();
+        window.backend().assert_text(&[
+            "┌───────┐",
+            "│###### │",
+            "│###### │",
+            "└───────┘",
+        ].join("\n"));
+
+        let mut buf = Buffer::new(6, 3);
+        let mut view = BufferView::new(&mut buf);
+        let mut child = view.sub_view(Rect::new(2, 1, 10, 10));
+        assert_eq!(child.absolute_area(), Rect::new(2, 1, 4, 2));
+        let mut grandchild = child.sub_view(Rect::new(1, 1, 2, 1));
+        assert_eq!(grandchild.absolute_area(), Rect::new(3, 2, 2, 1));
+        grandchild.push_rect_buffer(vec!["xyz".to_string()], vec![], Rect::new(0, 0, 3, 1));
+        assert_eq!(buf.get_buffer(), vec!["      ", "      ", "   xy "]);
+    }
+
+    #[test]
+    fn canvas_test() {
+        let mut canvas = Canvas::new(4, 2, Marker::Braille);
+        assert_eq!(canvas.size(), (8, 8));
+        canvas.rect(0, 0, 8, 8, None);
+        canvas.line((0, 0), (7, 7), None);
+        canvas.circle(20, 20, 3, None);
+        canvas.polygon(&[(-5, 4), (3, 4)], None);
+        assert!(canvas.is_set(3, 4) && !canvas.is_set(5, 4));
+
+        // shapes reaching far off the canvas only touch the visible pixels
+        let mut clipped = Canvas::new(4, 2, Marker::Braille);
+        clipped.fill_rect(isize::MAX - 1, 0, usize::MAX, 2, None);
+        clipped.fill_rect(-2, 6, usize::MAX, 5, None);
+        clipped.rect(isize::MAX - 3, 0, usize::MAX, 8, None);
+        clipped.line((isize::MIN, 0), (isize::MAX, 0), None);
+        clipped.line((-1_000_000_000, -1_000_000_000), (1_000_000_000, 1_000_000_000), None);
+        assert!((0..8).all(|x| clipped.is_set(x, 0) && clipped.is_set(x, 6) && clipped.is_set(x, 7)));
+        assert!((1..6).all(|i| clipped.is_set(i, i) && !clipped.is_set(7 - i, i)));
+        let mut round = Canvas::new(4, 2, Marker::Braille);
+        round.circle(0, 0, 2_000_000_000, None);
+        round.circle(isize::MIN, isize::MIN, usize::MAX, None);
+        round.circle(4, -1_000_000_000, 1_000_000_002, None);
+        assert!((0..8).all(|x| round.is_set(x, 2) && !round.is_set(x, 1) && !round.is_set(x, 3)));
+
+        let mut window = Window::new(TestBackend::new(5, 2));
+        window.draw(&mut canvas).unwrap();
+        window.backend().assert_text(&["⡟⢍⠉⢹ ", "⣏⣉⣑⣼ "].join("\n"));
+
+        let red = Color::from_rgb(255, 0, 0);
+        let blue = Color::from_rgb(0, 0, 255);
+        let mut canvas = Canvas::new(3, 1, Marker::HalfBlock).set_color(None, Some(Color::gray(0)));
+        canvas.point(0, 0, Some(&red));
+        canvas.fill_rect(1, 0, 2, 2, Some(&blue));
+        canvas.point(2, 1, Some(&red));
+        let mut window = Window::new(TestBackend::new(3, 1));
+        window.draw(&mut canvas).unwrap();
+        window.backend().assert_text("▀█▀");
+        let back = Some(Color::gray(0));
+        window.backend().assert_styles("abc", &[
+            ('a', Style { front: Some(red), back, ..Style::default() }),
+            ('b', Style { front: Some(blue), back, ..Style::default() }),
+            ('c', Style { front: Some(blue), back: Some(red), ..Style::default() }),
+        ]);
+    }
+
+    #[test]
+    fn writer_test() {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let back = Style { back: Some(Color::gray(2)), ..Style::default() };
+
+        let mut buf = Buffer::new(8, 3);
+        assert_eq!(buf.set_string(1, 0, "abあ", &bold), 4);
+        assert_eq!(buf.set_string_truncated(0, 1, "xyあz", 3, &Style::default()), 2);
+        // glyphs crossing the edge of the buffer are left out
+        assert_eq!(buf.set_string(6, 1, "いa", &Style::default()), 2);
+        assert_eq!(buf.set_string(7, 1, "う", &Style::default()), 0);
+        assert_eq!(buf.set_string(0, 10, "abc", &Style::default()), 0);
+        buf.fill(Rect::new(0, 2, 5, 1), 'あ', &Style::default());
+        buf.set_style(Rect::new(4, 0, 4, 3), &back);
+        assert_eq!(buf.get_cell(1, 0).unwrap().style, bold);
+        assert_eq!(buf.get_cell(3, 0).unwrap().symbol, "あ");
+        assert!(buf.get_cell(8, 0).is_none());
+
+        let mut window = Window::new(TestBackend::new(8, 3));
+        window.render(&mut buf).unwrap();
+        window.backend().assert_text(&[" abあ   ", "xy    い", "ああ    "].join("\n"));
+        window.backend().assert_styles(&[".aaaabbb", "....bbbb", "....bbbb"].join("\n"), &[('a', bold.clone()), ('b', back)]);
+
+        // views write in their own coordinates and stop at their edges
+        let mut buf = Buffer::new(8, 2);
+        let mut view = BufferView::new(&mut buf);
+        let mut child = view.sub_view(Rect::new(2, 1, 4, 1));
+        assert_eq!(child.set_string(1, 0, "abcdef", &bold), 3);
+        assert_eq!(child.set_string(0, 1, "abc", &bold), 0);
+        child.fill(Rect::new(0, 0, 1, 5), '-', &Style::default());
+        assert_eq!(child.get_cell(1, 0).unwrap().symbol, "a");
+        assert!(child.get_cell(4, 0).is_none());
+        assert_eq!(buf.get_rect(Rect::new(0, 1, 8, 1)), vec!["  -\x1b[1mabc\x1b[0m  "]);
+        // only the columns inside the clip count
+        buf.push_clip(Rect::new(3, 0, 2, 1));
+        assert_eq!(buf.set_string(1, 0, "abcd", &Style::default()), 2);
+        buf.pop_clip();
+        assert_eq!(buf.get_rect(Rect::new(0, 0, 8, 1)), vec!["   cd   "]);
+        // so do the visible halves of wide glyphs, as padding
+        buf.set_string(0, 0, "xxxxxxxx", &Style::default());
+        buf.push_clip(Rect::new(3, 0, 2, 1));
+        assert_eq!(buf.set_string(2, 0, "あb", &Style::default()), 2);
+        buf.pop_clip();
+        assert_eq!(buf.get_rect(Rect::new(0, 0, 8, 1)), vec!["xxx bxxx"]);
+    }
+
+    #[test]
+    fn cursor_test() {
+        let mut window = Window::new(MemoryBackend::new(6, 2));
+        let mut buf = Buffer::new(6, 2);
+        buf.set_string(0, 1, "> ab", &Style::default());
+        buf.set_cursor(4, 1);
+        buf.set_cursor_shape(CursorShape::BlinkingBar);
+        window.render(&mut buf).unwrap();
+        let out = String::from_utf8(window.backend_mut().take_output()).unwrap();
+        // the cursor is placed after the cells are drawn
+        assert!(out.ends_with("> ab  \x1b[2;5H\x1b[5 q\x1b[?25h"), "{:?}", out);
+
+        window.render(&mut buf).unwrap();
+        assert!(window.backend().output().is_empty());
+
+        buf.set_string(4, 1, "c", &Style::default());
+        buf.set_cursor(5, 1);
+        window.render(&mut buf).unwrap();
+        assert_eq!(window.backend_mut().take_output(), b"\x1b[2;5Hc\x1b[2;6H");
+
+        buf.hide_cursor();
+        window.render(&mut buf).unwrap();
+        assert_eq!(window.backend_mut().take_output(), b"\x1b[?25l");
+        window.restore_cursor().unwrap();
+        assert_eq!(window.backend_mut().take_output(), b"\x1b[0 q\x1b[?25h");
+
+        // views place the cursor in their own coordinates
+        let mut view = BufferView::new(&mut buf);
+        view.sub_view(Rect::new(2, 0, 3, 2)).set_cursor(1, 1);
+        assert_eq!(buf.cursor().position, Some((3, 1)));
+        buf.resize(3, 1);
+        assert_eq!(buf.cursor().position, None);
+
+        let mut window = Window::new(TestBackend::new(6, 2));
+        let mut buf = Buffer::new(6, 2);
+        buf.set_cursor(2, 0);
+        buf.set_cursor_shape(CursorShape::SteadyBlock);
+        window.render(&mut buf).unwrap();
+        assert_eq!(window.backend().cursor(), (2, 0));
+        assert!(window.backend().cursor_visible());
+        assert_eq!(window.backend().cursor_shape(), CursorShape::SteadyBlock);
+    }
+
+    #[test]
+    fn link_test() {
+        let open = "\x1b]8;;https://example.com\x1b\\";
+        let close = "\x1b]8;;\x1b\\";
+        let mut buf = Buffer::new(10, 2);
+        let effs = vec![vec![Effect::link("https://example.com", 0), Effect::end_link(4)]];
+        buf.push_rect_buffer(vec!["docs here".to_string()], effs.clone(), Rect::new(0, 0, 9, 1));
+        assert_eq!(buf.get_buffer()[0], format!("{}docs{} here ", open, close));
+
+        // an overlay splits the link, a rect cuts it off
+        buf.push_rect_buffer(vec!["X".to_string()], vec![], Rect::new(2, 0, 1, 1));
+        buf.push_rect_buffer(vec!["docs".to_string()], effs, Rect::new(7, 1, 2, 1));
+        assert_eq!(buf.get_buffer(), vec![
+            format!("{}do{}X{}s{} here ", open, close, open, close),
+            format!("       {}do{} ", open, close),
+        ]);
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        buf.set_style(Rect::new(0, 0, 10, 1), &bold);
+        assert_eq!(buf.get_buffer()[0], format!("\x1b[1m{}do{}X{}s{} here \x1b[0m", open, close, open, close));
+
+        let mut window = Window::new(MemoryBackend::new(10, 2));
+        window.render(&mut buf).unwrap();
+        let out = String::from_utf8(window.backend_mut().take_output()).unwrap();
+        assert!(out.contains(&format!("\x1b[0m       {}do{} ", open, close)), "{:?}", out);
+        assert!(export::to_html(&buf).contains("<a href=\"https://example.com\">do</a>"));
+
+        let (lines, effs) = ansi::parse("\x1b]8;;http://x\x1b\\a\x1b[1mb\x1b[0mc\x1b]8;;\x07d");
+        assert_eq!(lines, vec!["abcd"]);
+        let mut buf = Buffer::new(4, 1);
+        buf.push_rect_buffer(lines, effs, Rect::new(0, 0, 4, 1));
+        let links: Vec<_> = buf.row(0).iter().map(|cell| cell.style.link.as_deref()).collect();
+        assert_eq!(links, vec![Some("http://x"), Some("http://x"), Some("http://x"), None]);
+
+        // escapes can't be smuggled into a link
+        let (lines, effs) = ansi::parse("\x1b]8;;http://x\x1b[31mevil\x07");
+        assert_eq!(lines, vec!["evil"]);
+        let mut buf = Buffer::new(4, 1);
+        buf.push_rect_buffer(lines, effs, Rect::new(0, 0, 4, 1));
+        assert!(buf.row(0).iter().all(|cell| cell.style.link.is_none() && cell.style.front == Some(Color::Red)));
+        let mut buf = Buffer::new(4, 1);
+        buf.push_rect_buffer(vec!["a".to_string()], vec![vec![Effect::link("http://x\x1b[31m\u{e9}\x07", 0)]], Rect::new(0, 0, 1, 1));
+        assert_eq!(buf.row(0)[0].style.link.as_deref(), Some("http://x[31m%C3%A9"));
+        assert_eq!(buf.get_buffer()[0], "\x1b]8;;http://x[31m%C3%A9\x1b\\a\x1b]8;;\x1b\\   ");
+    }
+
+    #[test]
+    fn style_test() {
+        let red = Color::from_rgb(255, 0, 0);
+        let base = Style::default().with_front(red).add_modifier(Modifier::BOLD | Modifier::ITALIC);
+        let patch = Style::default().with_back(Color::indexed(4)).add_modifier(Modifier::UNDERLINE).remove_modifier(Modifier::BOLD);
+        let patched = base.clone().patch(&patch);
+        assert_eq!(patched.front, Some(red));
+        assert_eq!(patched.back, Some(Color::indexed(4)));
+        assert_eq!(patched.add_modifier, Modifier::ITALIC | Modifier::UNDERLINE);
+        assert_eq!(patched.sub_modifier, Modifier::BOLD);
+        // patching with nothing keeps the style
+        assert_eq!(base.clone().patch(&Style::default()), base);
+
+        // restyled cells keep what the new style doesn't change
+        let mut buf = Buffer::new(3, 1);
+        buf.set_string(0, 0, "abc", &base);
+        buf.set_style(Rect::new(0, 0, 2, 1), &Style::default().with_back(Color::indexed(4)));
+        BufferView::new(&mut buf).set_style(Rect::new(1, 0, 2, 1), &Style::default().remove_modifier(Modifier::BOLD));
+        let cells = buf.row(0);
+        assert_eq!((cells[0].style.back, cells[0].style.add_modifier), (Some(Color::indexed(4)), Modifier::BOLD | Modifier::ITALIC));
+        assert_eq!((cells[1].style.back, cells[1].style.add_modifier), (Some(Color::indexed(4)), Modifier::ITALIC));
+        assert_eq!((cells[2].style.front, cells[2].style.add_modifier), (Some(red), Modifier::ITALIC));
+
+        assert_eq!(format!("{:?}", patched.add_modifier), "ITALIC | UNDERLINE");
+        assert_eq!(Modifier::from_bits(patched.add_modifier.bits()), Some(patched.add_modifier));
+        assert_eq!(Modifier::from_bits(1 << 12), None);
+        assert_eq!(!Modifier::empty(), Modifier::all());
+
+        // removed modifiers are no part of what the terminal shows
+        assert_eq!(patched.transition(&base), "\x1b[22;4;48;5;4m");
+        assert_eq!(patched.to_seq(), "\x1b[0m\x1b[3m\x1b[4m\x1b[38;2;255;0;0m\x1b[48;5;4m");
+        let mut applied = Style::default();
+        for effect in patched.to_effects(0) {
+            applied.apply(&effect);
+        }
+        assert_eq!(applied, Style { sub_modifier: Modifier::empty(), ..patched });
+    }
+
+    #[test]
+    fn color_test() {
+        assert_eq!(Color::Red.to_front_seq(), "\x1b[31m");
+        assert_eq!(Color::BrightCyan.to_back_seq(), "\x1b[106m");
+        assert_eq!(Color::Indexed(1).to_front_seq(), "\x1b[38;5;1m");
+        assert_eq!(Color::Reset.to_back_seq(), "\x1b[49m");
+        assert_eq!(Color::ansi(9), Color::BrightRed);
+        assert_eq!(Color::Red.rgb(), Color::Indexed(1).rgb());
+        assert_eq!(Color::Reset.rgb(), None);
+
+        // palette colors and exact ones are told apart
+        let theme: std::collections::HashSet<_> = [Color::Red, Color::Indexed(1), Color::Rgb(205, 0, 0), Color::Red].into();
+        assert_eq!(theme.len(), 3);
+
+        // an explicit reset overrides a patched color but looks like none
+        let red = Style::default().with_front(Color::Red);
+        let reset = red.clone().patch(&Style::default().with_front(Color::Reset));
+        assert_eq!(reset.front, Some(Color::Reset));
+        assert_eq!(reset.transition(&red), "\x1b[0m");
+        assert_eq!(Style::default().transition(&reset), "");
+
+        assert_eq!(Color::Blue.with_alpha(255), Color::Blue);
+        assert_eq!(Color::White.with_alpha(0), Color::Rgba(229, 229, 229, 0));
+        assert_eq!(Color::from_rgba(1, 2, 3, 255), Color::Rgb(1, 2, 3));
+        assert_eq!(Color::from_rgba(1, 2, 3, 9).blend_over(&Color::Reset), Color::Rgba(1, 2, 3, 9));
+    }
+
+    #[test]
+    fn parse_color_test() {
+        let parse = |text: &str| text.parse::<Color>();
+        assert_eq!(parse("#1e1e2e"), Ok(Color::Rgb(0x1e, 0x1e, 0x2e)));
+        assert_eq!(parse("#FA0"), Ok(Color::Rgb(0xff, 0xaa, 0x00)));
+        assert_eq!(parse("#1e1e2e80"), Ok(Color::Rgba(0x1e, 0x1e, 0x2e, 0x80)));
+        assert_eq!(parse(" RebeccaPurple "), Ok(Color::Rgb(0x66, 0x33, 0x99)));
+        assert_eq!(parse("light-goldenrod-yellow"), Ok(Color::Rgb(0xfa, 0xfa, 0xd2)));
+        assert_eq!(parse("red"), Ok(Color::Red));
+        assert_eq!(parse("Bright_Blue"), Ok(Color::BrightBlue));
+        assert_eq!(parse("default"), Ok(Color::Reset));
+        assert_eq!(parse("transparent"), Ok(Color::Rgba(0, 0, 0, 0)));
+        assert_eq!(parse("196"), Ok(Color::Indexed(196)));
+        assert_eq!(parse("rgb(30, 30, 46)"), Ok(Color::Rgb(30, 30, 46)));
+        assert_eq!(parse("rgb(100% 0% 50% / 0.5)"), Ok(Color::Rgba(255, 0, 128, 128)));
+        assert_eq!(parse("rgba(1, 2, 3, 100%)"), Ok(Color::Rgb(1, 2, 3)));
+        assert_eq!(parse("hsl(220, 40%, 20%)"), Ok(Color::Rgb(31, 44, 71)));
+        assert_eq!(parse("hsl(-120deg 100% 50%)"), Ok(Color::Rgb(0, 0, 255)));
+        assert_eq!(parse("hsla(0, 0%, 100%, 0)"), Ok(Color::Rgba(255, 255, 255, 0)));
+
+        let error = |text: &str| parse(text).unwrap_err().to_string();
+        assert_eq!(error("#12345"), "invalid color \"#12345\": expected 3, 6 or 8 hex digits, found 5");
+        assert_eq!(error("#12g"), "invalid color \"#12g\": 'g' is no hex digit");
+        assert_eq!(error("rgb(1, 2)"), "invalid color \"rgb(1, 2)\": expected 3 components, found 2");
+        assert_eq!(error("rgb(1, 2, 300)"), "invalid color \"rgb(1, 2, 300)\": 300 is outside of 0 to 255");
+        assert_eq!(error("hsl(1, x, 2)"), "invalid color \"hsl(1, x, 2)\": 'x' is no number");
+        assert_eq!(error("rgb(1, 2, 3"), "invalid color \"rgb(1, 2, 3\": ')' is missing at the end");
+        assert_eq!(error("cmyk(1, 2, 3)"), "invalid color \"cmyk(1, 2, 3)\": cmyk() is no color function, expected rgb() or hsl()");
+        assert_eq!(error("256"), "invalid color \"256\": palette indexes go from 0 to 255");
+        assert_eq!(error("reddish"), "invalid color \"reddish\": no ANSI or CSS color has this name");
+        assert_eq!(parse("").unwrap_err().input(), "");
+    }
+
+    #[test]
+    fn depth_test() {
+        assert_eq!(Color::Rgb(255, 0, 0).downgrade(ColorDepth::Indexed256), Some(Color::Indexed(196)));
+        assert_eq!(Color::Rgb(128, 128, 128).downgrade(ColorDepth::Indexed256), Some(Color::Indexed(244)));
+        assert_eq!(Color::Rgb(250, 10, 20).downgrade(ColorDepth::Ansi16), Some(Color::BrightRed));
+        assert_eq!(Color::Rgb(0, 0, 230).downgrade(ColorDepth::Ansi16), Some(Color::Blue));
+        assert_eq!(Color::Rgb(30, 30, 30).downgrade(ColorDepth::Ansi16), Some(Color::Black));
+        assert_eq!(Color::Indexed(196).downgrade(ColorDepth::Ansi16), Some(Color::BrightRed));
+        assert_eq!(Color::Indexed(3).downgrade(ColorDepth::Ansi16), Some(Color::Yellow));
+        assert_eq!(Color::Indexed(196).downgrade(ColorDepth::Indexed256), Some(Color::Indexed(196)));
+        assert_eq!(Color::Magenta.downgrade(ColorDepth::Ansi16), Some(Color::Magenta));
+        assert_eq!(Color::Magenta.downgrade(ColorDepth::NoColor), None);
+
+        assert_eq!(backend::detect_color_depth("truecolor", "xterm-256color", Some(256)), ColorDepth::TrueColor);
+        assert_eq!(backend::detect_color_depth("", "screen-256color", None), ColorDepth::Indexed256);
+        assert_eq!(backend::detect_color_depth("", "xterm", Some(256)), ColorDepth::Indexed256);
+        assert_eq!(backend::detect_color_depth("", "linux", Some(8)), ColorDepth::Ansi16);
+        assert_eq!(backend::detect_color_depth("", "vt100", Some(0)), ColorDepth::NoColor);
+        assert_eq!(backend::detect_color_depth("", "dumb", None), ColorDepth::NoColor);
+
+        // a compiled entry: header, names, two booleans, a pad byte, numbers
+        let mut entry = vec![];
+        for short in [0o432, 7, 2, 14, 0, 0] {
+            entry.extend_from_slice(&(short as u16).to_le_bytes());
+        }
+        entry.extend_from_slice(b"x|test\0\x01\x00\x00");
+        for number in [80, -1, 24, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, 256] {
+            entry.extend_from_slice(&(number as i16).to_le_bytes());
+        }
+        assert_eq!(backend::terminfo::read_colors(&entry), Some(256));
+        assert_eq!(backend::terminfo::read_colors(&entry[..30]), None);
+
+        let mut window = Window::new(MemoryBackend::new(2, 1));
+        assert_eq!(window.color_depth(), ColorDepth::TrueColor);
+        window.set_color_depth(ColorDepth::Ansi16);
+        let mut buf = Buffer::new(2, 1);
+        buf.set_string(0, 0, "ab", &Style::default().with_front(Color::Rgb(0, 200, 0)).with_back(Color::Indexed(21)));
+        window.render(&mut buf).unwrap();
+        let out = String::from_utf8(window.backend_mut().take_output()).unwrap();
+        assert!(out.contains("\x1b[32;44mab\x1b[0m"), "{:?}", out);
+    }
+
+    #[test]
+    fn color_math_test() {
+        let base = Color::from_hsl(220.0, 0.4, 0.2);
+        let (h, s, l) = base.lighten(0.1).to_hsl().unwrap();
+        assert!((h - 220.0).abs() < 2.0 && (s - 0.4).abs() < 0.02 && (l - 0.3).abs() < 0.01, "{:?}", (h, s, l));
+        assert_eq!(base.lighten(0.1).darken(0.1), base);
+        assert_eq!(Color::Rgb(200, 100, 100).saturate(-1.0), Color::Rgb(150, 150, 150));
+        assert_eq!(Color::White.darken(1.0), Color::Rgb(0, 0, 0));
+        assert_eq!(Color::Rgba(0, 0, 0, 0).mix(&Color::Rgb(255, 255, 255), 0.5), Color::Rgba(128, 128, 128, 128));
+        assert_eq!(Color::Rgb(10, 20, 30).invert(), Color::Rgb(245, 235, 225));
+        assert_eq!(Color::Reset.lighten(0.5), Color::Reset);
+        assert_eq!(Color::Reset.to_hsl(), None);
+
+        let (l, a, b) = Color::Rgb(255, 255, 255).to_lab().unwrap();
+        assert!((l - 100.0).abs() < 0.1 && a.abs() < 0.1 && b.abs() < 0.1);
+        let ratio = Color::Rgb(0, 0, 0).contrast_ratio(&Color::Rgb(255, 255, 255)).unwrap();
+        assert!((ratio - 21.0).abs() < 0.001, "{}", ratio);
+        let ratio = Color::Rgb(0x77, 0x77, 0x77).contrast_ratio(&Color::Rgb(255, 255, 255)).unwrap();
+        assert!((ratio - 4.48).abs() < 0.01, "{}", ratio);
+        assert_eq!(Color::Reset.contrast_ratio(&Color::Red), None);
+
+        assert_eq!(Color::Rgb(70, 90, 255).contrast_text(), Color::Rgb(255, 255, 255));
+        assert_eq!(Color::Yellow.contrast_text(), Color::Rgb(0, 0, 0));
+        assert_eq!(Color::Reset.contrast_text(), Color::Reset);
+        let rect = Rect::new(0, 0, 2, 1);
+        let mut buf = Buffer::new(2, 1);
+        buf.push_rect_buffer(vec!["ok".to_string()], rect.make_contrast_eff_vec(Color::gray(2)), rect);
+        assert_eq!(buf.row(0)[0].style.front, Some(Color::Rgb(255, 255, 255)));
+    }
+
+    #[test]
+    fn sync_test() {
+        assert!(backend::detect_sync("xterm-kitty", ""));
+        assert!(backend::detect_sync("xterm-256color", "WezTerm"));
+        assert!(!backend::detect_sync("xterm-256color", "Apple_Terminal"));
+
+        let mut window = Window::new(MemoryBackend::new(4, 1));
+        assert!(!window.is_sync());
+        window.set_sync(true);
+        let mut buf = Buffer::new(4, 1);
+        buf.set_string(0, 0, "ab", &Style::default());
+        window.render(&mut buf).unwrap();
+        let out = String::from_utf8(window.backend_mut().take_output()).unwrap();
+        assert!(out.starts_with("\x1b[?2026h\x1b[2J"), "{:?}", out);
+        assert!(out.ends_with("\x1b[?25l\x1b[?2026l"), "{:?}", out);
+    }
+
+    #[test]
+    #[cfg(feature = "termion")]
+    fn single_write_test() {
+        use crate::backend::Backend;
+        use crate::backend::termion::TermionBackend;
+
+        // the whole frame reaches the writer at once
+        struct Writes(Vec<usize>);
+        impl std::io::Write for Writes {
+            fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+                self.0.push(data.len());
+                Ok(data.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let mut backend = TermionBackend::with_tty(Writes(vec![]), true);
+        let written = backend.begin_sync().unwrap() + backend.goto(1, 0).unwrap()
+            + backend.print("c").unwrap() + backend.end_sync().unwrap();
+        assert!(backend.get_ref().0.is_empty());
+        backend.flush().unwrap();
+        assert_eq!(backend.get_ref().0, vec![written]);
+
+        // colors depend on where the writer goes, not on stdout
+        let file = std::fs::OpenOptions::new().write(true).open("/dev/null").unwrap();
+        assert_eq!(TermionBackend::new(file).color_depth(), ColorDepth::NoColor);
+        assert_eq!(TermionBackend::with_tty(Writes(vec![]), false).color_depth(), ColorDepth::NoColor);
+
+        // a log file needs no terminal to take its size from
+        let mut window = Window::new(TermionBackend::with_size(Vec::new(), 4, 2));
+        window.render(&mut Buffer::new(4, 2)).unwrap();
+        assert_eq!(window.backend().size().unwrap(), (4, 2));
+        assert!(!window.backend().get_ref().is_empty());
+    }
+
+    #[test]
+    fn diff_test() {
+        let mut window = Window::new(MemoryBackend::new(6, 2));
+
+        let mut buf = Buffer::new(6, 2);
+        buf.push_rect_buffer(vec!["abc".to_string()], vec![], Rect::new(0, 0, 3, 1));
+        let full = window.render(&mut buf).unwrap();
+        assert_eq!(full, window.backend_mut().take_output().len());
+
+        assert_eq!(window.render(&mut buf).unwrap(), 0);
+
+        let effs = Rect::new(0, 0, 1, 1).make_color_eff_vec(None, Some(Color::gray(0)));
+        buf.push_rect_buffer(vec!["x".to_string()], effs, Rect::new(4, 1, 1, 1));
+        let written = window.render(&mut buf).unwrap();
+        let out = window.backend_mut().take_output();
+        assert_eq!(String::from_utf8(out).unwrap(), "\x1b[2;5H\x1b[48;5;232mx\x1b[0m");
+        assert!(written < full);
+    }
+
     #[test]
     fn rep_test() {
         let line = " ".to_string().repeat(120);
         let start1 = time::Instant::now();
-        let mut buf1 = std::iter::repeat(line.clone()).take(30);
+        let _buf1: Vec<String> = std::iter::repeat_n(line.clone(), 30).collect();
         let end1 = start1.elapsed().as_micros();
 
         let start2 = time::Instant::now();
         let mut buf2 = vec![];
-        for i in 0..30 {
+        for _ in 0..30 {
             buf2.push(line.clone());
         }
         let end2 = start2.elapsed().as_micros();
@@ -129,7 +920,6 @@ mod tests {
         // println!("run time : {}s", end.as_nanos() as f64 / 1_000_000_000.0);
         // println!("run time : {}s / frame", end.as_nanos() as f64 / 1_000_000_000.0 / 1000.0)
 
-        print!("\x1b[2J");
         let mut ver_lay = Vertical::new(
             Constraint::percentage(100, 200, 0),
             Alignment::Start
@@ -163,22 +953,83 @@ mod tests {
             .set_color(Some(Color::from_rgb(255, 255, 255)), Some(Color::from_rgb(100, 100, 100)))
         ));
         
-        let (width, height) = termion::terminal_size().unwrap();
-        let mut buf = Buffer::new(width as usize, height as usize);
-        let rect = Rect::new(0, 0, width as usize, height as usize);
+        let mut window = Window::new(MemoryBackend::new(100, 30));
+        let written = window.draw(&mut ver_lay).unwrap();
+        let output = window.backend_mut().take_output();
+        assert_eq!(written, output.len());
+        print!("{}", String::from_utf8_lossy(&output));
 
-        ver_lay.write_buffer(&mut buf, rect);
+        // nothing changed, so nothing is sent again
+        assert_eq!(window.draw(&mut ver_lay).unwrap(), 0);
+    }
 
-        let text = buf.get_buffer();
+    #[test]
+    fn border_test() {
+        let mut window = Window::new(TestBackend::new(8, 4));
+        let mut lay = Horizontal::new(Constraint::percentage(100, 100, 0), Alignment::Start)
+            .set_border(Some(border::SINGLE_ROUNDED))
+            .add(Box::from(
+                Vertical::new(Constraint::constant(2), Alignment::End)
+                .set_color(None, Some(Color::gray(4)))
+            ));
+        window.draw(&mut lay).unwrap();
 
-        for l in 0..text.len() {
-            print!("\x1b[{};1H", l + 1);
-            print!("\x1b[2K");
-            print!("{}", text[l].as_str());
-            if l != text.len() - 1 {
-                print!("\n");
-            }
+        window.backend().assert_text(
+            "╭──────╮\n\
+             │      │\n\
+             │      │\n\
+             ╰──────╯"
+        );
+
+        let mut back = Style::default();
+        back.apply(&Effect::back_color(Color::gray(4), 0));
+        window.backend().assert_styles(
+            "........\n\
+             .....bb.\n\
+             .....bb.\n\
+             ........",
+            &[('b', back)]
+        );
+    }
+
+    #[test]
+    fn snapshot_test() {
+        let mut lay = Vertical::new(Constraint::percentage(100, 100, 0), Alignment::Start)
+            .set_border(Some(border::DOUBLE))
+            .set_color(Some(Color::from_rgb(255, 255, 255)), Some(Color::from_rgb(40, 40, 40)))
+            .add(Box::from(
+                Horizontal::new(Constraint::constant(3), Alignment::Start)
+                .set_border(Some(border::SINGLE))
+                .set_color(None, Some(Color::from_rgb_8bit(0, 2, 4)))
+            ))
+            .add(Box::from(
+                Horizontal::new(Constraint::constant(1), Alignment::End)
+                .set_color(Some(Color::gray(0)), Some(Color::gray(20)))
+            ));
+
+        let mut window = Window::new(TestBackend::new(24, 8));
+        window.draw(&mut lay).unwrap();
+        window.backend().assert_snapshot(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots/snapshot_test.snap"));
+    }
+
+    #[test]
+    #[should_panic(expected = "run with UPDATE_SNAPSHOTS=1 to create it")]
+    fn missing_snapshot_test() {
+        let window = Window::new(TestBackend::new(2, 1));
+        window.backend().assert_snapshot_with(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots/missing.snap"), false);
+    }
+
+    #[test]
+    fn style_key_test() {
+        let mut buf = Buffer::new(30, 1);
+        for x in 0..30 {
+            buf.set_style(Rect::new(x, 0, 1, 1), &Style::default().with_front(Color::Indexed(x as u8)));
         }
+        let mut window = Window::new(TestBackend::new(30, 1));
+        window.render(&mut buf).unwrap();
+        let (map, styles) = window.backend().style_map();
+        assert_eq!(map, "abcdefghijklmnopqrstuvwxyzABCD");
+        assert_eq!(styles.len(), 30);
     }
 
     #[test]
@@ -196,6 +1047,10 @@ mod tests {
             back_col.to_string().as_str(),
             Effect::reset(1).to_string().as_str(),
         );
+
+        assert_eq!(front_col.to_string(), "\x1b[38;2;0;0;0m");
+        assert_eq!(back_col.to_string(), "\x1b[48;2;30;170;150m");
+        assert_eq!(Effect::reset(1).to_string(), "\x1b[0m");
     }
 }
 
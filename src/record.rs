@@ -0,0 +1,419 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::ansi::Screen;
+use crate::backend::Backend;
+use crate::buffer::{Buffer, CursorShape};
+use crate::effect::{ColorDepth, Style};
+use crate::timer::Timer;
+
+/// Collects timestamped terminal output and saves it as an asciicast v2
+/// recording.
+pub struct Recorder {
+    width: usize,
+    height: usize,
+    // start of the recording in seconds since the epoch
+    timestamp: u64,
+    events: Vec<(Duration, String)>,
+}
+
+impl Recorder {
+    pub fn new(width: usize, height: usize) -> Self {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|t| t.as_secs()).unwrap_or(0);
+        Recorder { width, height, timestamp, events: vec![] }
+    }
+
+    pub fn events(&self) -> &[(Duration, String)] {
+        &self.events
+    }
+
+    pub fn record(&mut self, time: Duration, output: &str) {
+        if !output.is_empty() {
+            self.events.push((time, output.to_string()));
+        }
+    }
+
+    /// Records the whole buffer as one frame, stamped with the time of the
+    /// frame clock.
+    pub fn record_frame(&mut self, timer: &Timer, buffer: &Buffer) {
+        let frame = "\x1b[H".to_string() + &buffer.get_buffer().join("\r\n");
+        self.record(timer.elapsed(), &frame);
+    }
+
+    pub fn to_asciicast(&self) -> String {
+        let mut cast = format!(
+            "{{\"version\": 2, \"width\": {}, \"height\": {}, \"timestamp\": {}}}\n",
+            self.width, self.height, self.timestamp
+        );
+        for (time, output) in &self.events {
+            cast += &format!("[{:.6}, \"o\", {}]\n", time.as_secs_f64(), json::quote(output));
+        }
+        cast
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::File::create(path)?.write_all(self.to_asciicast().as_bytes())
+    }
+}
+
+/// Backend wrapper which records everything sent through it, one event per
+/// flushed frame.
+pub struct RecordingBackend<B: Backend> {
+    inner: B,
+    recorder: Recorder,
+    start: Instant,
+    frame: String,
+}
+
+impl<B: Backend> RecordingBackend<B> {
+    pub fn new(inner: B) -> io::Result<Self> {
+        let (width, height) = inner.size()?;
+        Ok(RecordingBackend { inner, recorder: Recorder::new(width, height), start: Instant::now(), frame: String::new() })
+    }
+
+    pub fn recorder(&self) -> &Recorder {
+        &self.recorder
+    }
+
+    pub fn into_parts(self) -> (B, Recorder) {
+        (self.inner, self.recorder)
+    }
+}
+
+impl<B: Backend> Backend for RecordingBackend<B> {
+    fn size(&self) -> io::Result<(usize, usize)> {
+        self.inner.size()
+    }
+
+    fn goto(&mut self, x: usize, y: usize) -> io::Result<usize> {
+        self.frame += &format!("\x1b[{};{}H", y + 1, x + 1);
+        self.inner.goto(x, y)
+    }
+
+    fn set_style(&mut self, from: &Style, to: &Style) -> io::Result<usize> {
+        self.frame += &to.transition(from);
+        self.inner.set_style(from, to)
+    }
+
+    fn print(&mut self, text: &str) -> io::Result<usize> {
+        self.frame += text;
+        self.inner.print(text)
+    }
+
+    fn clear(&mut self) -> io::Result<usize> {
+        self.frame += "\x1b[2J";
+        self.inner.clear()
+    }
+
+    fn show_cursor(&mut self) -> io::Result<usize> {
+        self.frame += "\x1b[?25h";
+        self.inner.show_cursor()
+    }
+
+    fn hide_cursor(&mut self) -> io::Result<usize> {
+        self.frame += "\x1b[?25l";
+        self.inner.hide_cursor()
+    }
+
+    fn set_cursor_shape(&mut self, shape: CursorShape) -> io::Result<usize> {
+        self.frame += &shape.to_seq();
+        self.inner.set_cursor_shape(shape)
+    }
+
+    fn begin_sync(&mut self) -> io::Result<usize> {
+        self.frame += "\x1b[?2026h";
+        self.inner.begin_sync()
+    }
+
+    fn end_sync(&mut self) -> io::Result<usize> {
+        self.frame += "\x1b[?2026l";
+        self.inner.end_sync()
+    }
+
+    fn supports_sync(&self) -> bool {
+        self.inner.supports_sync()
+    }
+
+    fn color_depth(&self) -> ColorDepth {
+        self.inner.color_depth()
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let frame = std::mem::take(&mut self.frame);
+        self.recorder.record(self.start.elapsed(), &frame);
+        self.inner.flush()
+    }
+}
+
+// Screens replayed from a cast hold at most this many cells.
+const MAX_CELLS: usize = 1 << 20;
+
+/// Replays an asciicast v2 recording into buffers.
+pub struct Player {
+    width: usize,
+    height: usize,
+    events: Vec<(Duration, String)>,
+}
+
+impl Player {
+    pub fn parse(cast: &str) -> io::Result<Self> {
+        let mut lines = cast.lines().filter(|line| !line.trim().is_empty());
+        let header = json::parse(lines.next().unwrap_or(""))?;
+        if header.get("version").and_then(|v| v.as_number()) != Some(2.0) {
+            return Err(invalid("only asciicast version 2 is supported"));
+        }
+        // terminal sizes are 16 bit, anything beyond is no real recording
+        let size = |key: &str| match header.get(key).and_then(|v| v.as_number()) {
+            Some(v) if (0.0..=u16::MAX as f64).contains(&v) && v.fract() == 0.0 => Ok(v as usize),
+            Some(v) => Err(invalid(&format!("the header {} {} is no terminal size", key, v))),
+            None => Err(invalid(&format!("the header has no {}", key))),
+        };
+        let (width, height) = (size("width")?, size("height")?);
+        if width * height > MAX_CELLS {
+            return Err(invalid(&format!("a {}x{} screen is too large to replay", width, height)));
+        }
+
+        let mut events = vec![];
+        for line in lines {
+            let event = json::parse(line)?;
+            let (time, kind, data) = match event.as_array() {
+                Some([time, kind, data]) => (time.as_number(), kind.as_str(), data.as_str()),
+                _ => return Err(invalid(&format!("malformed event: {}", line))),
+            };
+            match (time, kind, data) {
+                (Some(time), Some("o"), Some(data)) => {
+                    let time = Duration::try_from_secs_f64(time)
+                        .map_err(|_| invalid(&format!("event time out of range: {}", line)))?;
+                    events.push((time, data.to_string()));
+                }
+                (Some(_), Some(_), Some(_)) => {}
+                _ => return Err(invalid(&format!("malformed event: {}", line))),
+            }
+        }
+
+        Ok(Player { width, height, events })
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Player::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.events.last().map(|(time, _)| *time).unwrap_or_default()
+    }
+
+    /// Screen contents after all output up to `time`.
+    pub fn replay(&self, time: Duration) -> Buffer {
+        let mut screen = Screen::new(self.width, self.height);
+        for (_, output) in self.events.iter().take_while(|(t, _)| *t <= time) {
+            screen.feed(output);
+        }
+        screen.into_buffer()
+    }
+
+    /// Screen contents after every event, with the event's time.
+    pub fn frames(&self) -> Vec<(Duration, Buffer)> {
+        let mut screen = Screen::new(self.width, self.height);
+        let mut frames = vec![];
+        for (time, output) in &self.events {
+            screen.feed(output);
+            frames.push((*time, screen.buffer().clone()));
+        }
+        frames
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+// The little JSON asciicast files need.
+mod json {
+    use std::io;
+    use std::iter::Peekable;
+    use std::str::Chars;
+
+    use super::invalid;
+
+    // nesting deeper than this is rejected instead of recursing on
+    const MAX_DEPTH: usize = 64;
+
+    pub enum Value {
+        // true, false and null, whose values asciicast never needs
+        Literal,
+        Number(f64),
+        Str(String),
+        Array(Vec<Value>),
+        Object(Vec<(String, Value)>),
+    }
+
+    impl Value {
+        pub fn get(&self, key: &str) -> Option<&Value> {
+            match self {
+                Value::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+                _ => None,
+            }
+        }
+
+        pub fn as_number(&self) -> Option<f64> {
+            match self {
+                Value::Number(n) => Some(*n),
+                _ => None,
+            }
+        }
+
+        pub fn as_str(&self) -> Option<&str> {
+            match self {
+                Value::Str(s) => Some(s),
+                _ => None,
+            }
+        }
+
+        pub fn as_array(&self) -> Option<&[Value]> {
+            match self {
+                Value::Array(items) => Some(items),
+                _ => None,
+            }
+        }
+    }
+
+    pub fn quote(text: &str) -> String {
+        let mut quoted = "\"".to_string();
+        for ch in text.chars() {
+            match ch {
+                '"' => quoted += "\\\"",
+                '\\' => quoted += "\\\\",
+                '\n' => quoted += "\\n",
+                '\r' => quoted += "\\r",
+                '\t' => quoted += "\\t",
+                c if (c as u32) < 0x20 || c == '\x7f' => quoted += &format!("\\u{:04x}", c as u32),
+                c => quoted.push(c),
+            }
+        }
+        quoted + "\""
+    }
+
+    pub fn parse(text: &str) -> io::Result<Value> {
+        let mut chars = text.chars().peekable();
+        let value = parse_value(&mut chars, 0)?;
+        skip_space(&mut chars);
+        match chars.next() {
+            None => Ok(value),
+            Some(c) => Err(invalid(&format!("unexpected '{}' after JSON value", c))),
+        }
+    }
+
+    fn skip_space(chars: &mut Peekable<Chars>) {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(chars: &mut Peekable<Chars>, word: &str) -> io::Result<()> {
+        for expected in word.chars() {
+            if chars.next() != Some(expected) {
+                return Err(invalid(&format!("expected '{}'", word)));
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_value(chars: &mut Peekable<Chars>, depth: usize) -> io::Result<Value> {
+        skip_space(chars);
+        if depth >= MAX_DEPTH && matches!(chars.peek(), Some('[' | '{')) {
+            return Err(invalid(&format!("JSON nested deeper than {} levels", MAX_DEPTH)));
+        }
+        match chars.peek() {
+            Some('n') => expect(chars, "null").map(|_| Value::Literal),
+            Some('t') => expect(chars, "true").map(|_| Value::Literal),
+            Some('f') => expect(chars, "false").map(|_| Value::Literal),
+            Some('"') => parse_string(chars).map(Value::Str),
+            Some('[') => {
+                chars.next();
+                let mut items = vec![];
+                skip_space(chars);
+                if chars.next_if_eq(&']').is_some() {
+                    return Ok(Value::Array(items));
+                }
+                loop {
+                    items.push(parse_value(chars, depth + 1)?);
+                    skip_space(chars);
+                    match chars.next() {
+                        Some(',') => continue,
+                        Some(']') => return Ok(Value::Array(items)),
+                        _ => return Err(invalid("expected ',' or ']'")),
+                    }
+                }
+            }
+            Some('{') => {
+                chars.next();
+                let mut fields = vec![];
+                skip_space(chars);
+                if chars.next_if_eq(&'}').is_some() {
+                    return Ok(Value::Object(fields));
+                }
+                loop {
+                    skip_space(chars);
+                    let key = parse_string(chars)?;
+                    skip_space(chars);
+                    expect(chars, ":")?;
+                    fields.push((key, parse_value(chars, depth + 1)?));
+                    skip_space(chars);
+                    match chars.next() {
+                        Some(',') => continue,
+                        Some('}') => return Ok(Value::Object(fields)),
+                        _ => return Err(invalid("expected ',' or '}'")),
+                    }
+                }
+            }
+            Some(c) if *c == '-' || c.is_ascii_digit() => {
+                let mut number = String::new();
+                while let Some(c) = chars.next_if(|c| "+-.eE".contains(*c) || c.is_ascii_digit()) {
+                    number.push(c);
+                }
+                number.parse().map(Value::Number).map_err(|_| invalid(&format!("bad number '{}'", number)))
+            }
+            _ => Err(invalid("expected a JSON value")),
+        }
+    }
+
+    fn parse_string(chars: &mut Peekable<Chars>) -> io::Result<String> {
+        expect(chars, "\"")?;
+        let mut text = String::new();
+        loop {
+            match chars.next() {
+                Some('"') => return Ok(text),
+                Some('\\') => match chars.next() {
+                    Some('n') => text.push('\n'),
+                    Some('r') => text.push('\r'),
+                    Some('t') => text.push('\t'),
+                    Some('b') => text.push('\x08'),
+                    Some('f') => text.push('\x0c'),
+                    Some('u') => {
+                        let mut code = hex4(chars)?;
+                        // surrogate pairs encode characters outside the BMP
+                        if (0xd800..0xdc00).contains(&code) {
+                            expect(chars, "\\u")?;
+                            let low = hex4(chars)?;
+                            code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+                        }
+                        text.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                    }
+                    Some(c) => text.push(c),
+                    None => return Err(invalid("unterminated string")),
+                },
+                Some(c) => text.push(c),
+                None => return Err(invalid("unterminated string")),
+            }
+        }
+    }
+
+    fn hex4(chars: &mut Peekable<Chars>) -> io::Result<u32> {
+        let digits: String = chars.take(4).collect();
+        u32::from_str_radix(&digits, 16).map_err(|_| invalid(&format!("bad escape '\\u{}'", digits)))
+    }
+}
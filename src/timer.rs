@@ -2,12 +2,19 @@ use std::{time::{Duration, Instant}, thread::sleep};
 
 pub struct Timer {
     rate: Duration,
+    start: Instant,
     last: Instant,    
 }
 
 impl Timer {
     pub fn new(rate: Duration) -> Self {
-        Timer { rate: rate, last: Instant::now() }
+        let now = Instant::now();
+        Timer { rate, start: now, last: now }
+    }
+
+    /// Time since the timer was created, used to stamp recorded frames.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
     }
 
     // pub fn frame_wait(&mut self) -> bool {
@@ -26,12 +33,11 @@ impl Timer {
         let end = self.last.elapsed();
         self.last = Instant::now();
 
-        let dur = self.rate.checked_sub(end);
-        if dur.is_some() {
-            sleep(dur.unwrap());
-            return true;
+        if let Some(dur) = self.rate.checked_sub(end) {
+            sleep(dur);
+            true
         } else {
-            return false;
+            false
         }
     }
 }
\ No newline at end of file
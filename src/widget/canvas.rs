@@ -0,0 +1,330 @@
+use crate::buffer::{BufferView, Rect};
+use crate::effect::{Color, Style};
+use super::layout::{Alignment, Constraint};
+use super::widget::Widget;
+
+// braille dot of each pixel in a 2x4 cell, by row then column
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// How the pixels of a `Canvas` map to cells.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Marker {
+    /// 2x4 pixels per cell. All dots of a cell share the color drawn last.
+    Braille,
+    /// 1x2 pixels per cell, each pixel with its own color.
+    HalfBlock,
+}
+
+impl Marker {
+    // pixels per cell as (columns, rows)
+    fn pixels(&self) -> (usize, usize) {
+        match self {
+            Marker::Braille => (2, 4),
+            Marker::HalfBlock => (1, 2),
+        }
+    }
+}
+
+/// Widget with a pixel grid finer than the cells, for plots and maps.
+/// Coordinates are pixels from the top left, shapes may reach outside the
+/// canvas and are cut at its edges.
+pub struct Canvas {
+    constraint: Constraint,
+    alignment: Alignment,
+    marker: Marker,
+    columns: usize,
+    rows: usize,
+    // lit pixels with their color, if they have one
+    pixels: Vec<Option<Option<Color>>>,
+    // color of every cell for braille, where the dots can't differ
+    cell_colors: Vec<Option<Color>>,
+    front_color: Option<Color>,
+    back_color: Option<Color>,
+}
+
+impl Canvas {
+    /// Canvas covering `columns` x `rows` cells.
+    pub fn new(columns: usize, rows: usize, marker: Marker) -> Self {
+        let (px, py) = marker.pixels();
+        Canvas {
+            constraint: Constraint::percentage(100, usize::MAX, 0),
+            alignment: Alignment::Start,
+            marker,
+            columns,
+            rows,
+            pixels: vec![None; columns * px * rows * py],
+            cell_colors: vec![None; columns * rows],
+            front_color: None,
+            back_color: None,
+        }
+    }
+
+    pub fn set_layout(mut self, constraint: Constraint, alignment: Alignment) -> Self {
+        self.constraint = constraint;
+        self.alignment = alignment;
+        self
+    }
+
+    /// Colors of the cells without pixels, `front` also colors pixels drawn
+    /// without a color.
+    pub fn set_color(mut self, front: Option<Color>, back: Option<Color>) -> Self {
+        self.front_color = front;
+        self.back_color = back;
+        self
+    }
+
+    /// Size of the pixel grid as (width, height).
+    pub fn size(&self) -> (usize, usize) {
+        let (px, py) = self.marker.pixels();
+        (self.columns * px, self.rows * py)
+    }
+
+    pub fn clear(&mut self) {
+        self.pixels.iter_mut().for_each(|pixel| *pixel = None);
+        self.cell_colors.iter_mut().for_each(|color| *color = None);
+    }
+
+    pub fn is_set(&self, x: usize, y: usize) -> bool {
+        let (width, height) = self.size();
+        x < width && y < height && self.pixels[y * width + x].is_some()
+    }
+
+    pub fn point(&mut self, x: isize, y: isize, color: Option<&Color>) {
+        let (width, height) = self.size();
+        if x < 0 || y < 0 || x as usize >= width || y as usize >= height {
+            return;
+        }
+        let (x, y) = (x as usize, y as usize);
+        let color = color.or(self.front_color.as_ref()).cloned();
+
+        let (px, py) = self.marker.pixels();
+        self.cell_colors[y / py * self.columns + x / px] = color;
+        self.pixels[y * width + x] = Some(color);
+    }
+
+    /// Straight line between both points, both ends included.
+    pub fn line(&mut self, start: (isize, isize), end: (isize, isize), color: Option<&Color>) {
+        let Some(((x0, y0), (x1, y1))) = self.clip_line(start, end) else {
+            return;
+        };
+        // Bresenham's algorithm
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (sx, sy) = ((x1 - x0).signum(), (y1 - y0).signum());
+        let (mut x, mut y) = (x0, y0);
+        let mut err = dx + dy;
+        loop {
+            self.point(x, y, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Outline of a rectangle of `width` x `height` pixels.
+    pub fn rect(&mut self, x: isize, y: isize, width: usize, height: usize, color: Option<&Color>) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        let (right, bottom) = (x.saturating_add(to_isize(width) - 1), y.saturating_add(to_isize(height) - 1));
+        self.polygon(&[(x, y), (right, y), (right, bottom), (x, bottom)], color);
+    }
+
+    pub fn fill_rect(&mut self, x: isize, y: isize, width: usize, height: usize, color: Option<&Color>) {
+        let (canvas_width, canvas_height) = self.size();
+        // the pixels of the rect which are on the canvas
+        let visible = |start: isize, len: usize, size: usize| {
+            let end = start.saturating_add(to_isize(len));
+            start.clamp(0, to_isize(size))..end.clamp(0, to_isize(size))
+        };
+        for row in visible(y, height, canvas_height) {
+            for col in visible(x, width, canvas_width) {
+                self.point(col, row, color);
+            }
+        }
+    }
+
+    /// Outline of a circle around (`cx`, `cy`).
+    pub fn circle(&mut self, cx: isize, cy: isize, radius: usize, color: Option<&Color>) {
+        let (width, height) = self.size();
+        let (cx, cy, r) = (cx as i128, cy as i128, radius as i128);
+        if cx + r < 0 || cy + r < 0 || cx - r >= width as i128 || cy - r >= height as i128 {
+            return;
+        }
+
+        // Midpoint circle algorithm, one octant mirrored eight times. The
+        // offset across each octant is worked out for the rows and columns
+        // of the grid only, rather than stepping along the whole outline.
+        let reach = |y: i128| {
+            if y == 0 {
+                return Some(r);
+            }
+            let rest = r.checked_mul(r)?.checked_sub(y.checked_mul(y)?).filter(|rest| *rest > 0)?;
+            // the largest x whose midpoint x - 1/2 is inside the circle
+            let mut x = ((rest as f64).sqrt() + 0.5) as i128;
+            while x > 0 && x * (x - 1) >= rest {
+                x -= 1;
+            }
+            while (x + 1) * x < rest {
+                x += 1;
+            }
+            Some(x).filter(|x| *x >= y)
+        };
+        let mut plot = |x: i128, y: i128| {
+            if let (Ok(x), Ok(y)) = (isize::try_from(x), isize::try_from(y)) {
+                self.point(x, y, color);
+            }
+        };
+        for row in 0..height as i128 {
+            if let Some(x) = reach((row - cy).abs()) {
+                plot(cx + x, row);
+                plot(cx - x, row);
+            }
+        }
+        for col in 0..width as i128 {
+            if let Some(y) = reach((col - cx).abs()) {
+                plot(col, cy + y);
+                plot(col, cy - y);
+            }
+        }
+    }
+
+    /// Closed outline through all `points`.
+    pub fn polygon(&mut self, points: &[(isize, isize)], color: Option<&Color>) {
+        for (idx, start) in points.iter().enumerate() {
+            self.line(*start, points[(idx + 1) % points.len()], color);
+        }
+    }
+
+    // The part of the segment on the canvas, `None` if it misses it.
+    fn clip_line(&self, start: (isize, isize), end: (isize, isize)) -> Option<((isize, isize), (isize, isize))> {
+        let (width, height) = self.size();
+        let inside = |(x, y): (isize, isize)| x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height;
+        if inside(start) && inside(end) {
+            return Some((start, end));
+        }
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        // Cohen-Sutherland: move the ends outside onto the edges of the grid,
+        // taking each crossing from the deltas to keep precision on long lines
+        let (right, bottom) = ((width - 1) as f64, (height - 1) as f64);
+        let outcode = |(x, y): (f64, f64)| {
+            (x < 0.0) as u8 | ((x > right) as u8) << 1 | ((y < 0.0) as u8) << 2 | ((y > bottom) as u8) << 3
+        };
+        let (mut a, mut b) = ((start.0 as f64, start.1 as f64), (end.0 as f64, end.1 as f64));
+        // each end is moved at most twice, the last round accepts or rejects
+        for _ in 0..5 {
+            let (code_a, code_b) = (outcode(a), outcode(b));
+            if code_a | code_b == 0 {
+                break;
+            }
+            if code_a & code_b != 0 {
+                return None;
+            }
+            let code = if code_a != 0 { code_a } else { code_b };
+            let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+            let point = if code & 1 != 0 {
+                (0.0, a.1 - a.0 * dy / dx)
+            } else if code & 2 != 0 {
+                (right, a.1 + (right - a.0) * dy / dx)
+            } else if code & 4 != 0 {
+                (a.0 - a.1 * dx / dy, 0.0)
+            } else {
+                (a.0 + (bottom - a.1) * dx / dy, bottom)
+            };
+            if code == code_a {
+                a = point;
+            } else {
+                b = point;
+            }
+        }
+        let at = |(x, y): (f64, f64)| (x.round().clamp(0.0, right) as isize, y.round().clamp(0.0, bottom) as isize);
+        Some((at(a), at(b)))
+    }
+
+    // Symbol and style of the cell at `column`, `row`.
+    fn cell(&self, column: usize, row: usize) -> (String, Style) {
+        let (width, _) = self.size();
+        let back = Style { back: self.back_color, ..Style::default() };
+        match self.marker {
+            Marker::Braille => {
+                let mut bits = 0;
+                for (dy, dots) in BRAILLE_DOTS.iter().enumerate() {
+                    for (dx, dot) in dots.iter().enumerate() {
+                        if self.pixels[(row * 4 + dy) * width + column * 2 + dx].is_some() {
+                            bits |= dot;
+                        }
+                    }
+                }
+                if bits == 0 {
+                    return (" ".to_string(), back);
+                }
+                let symbol = char::from_u32(0x2800 + bits).unwrap_or(' ').to_string();
+                (symbol, Style { front: self.cell_colors[row * self.columns + column], ..back })
+            }
+            Marker::HalfBlock => {
+                let top = &self.pixels[row * 2 * width + column];
+                let bottom = &self.pixels[(row * 2 + 1) * width + column];
+                match (top, bottom) {
+                    (None, None) => (" ".to_string(), back),
+                    (Some(top), Some(bottom)) if top == bottom => ("█".to_string(), Style { front: *top, ..back }),
+                    // only the foreground can be the default color
+                    (Some(top), Some(None)) => ("▄".to_string(), Style { back: *top, ..Style::default() }),
+                    (Some(top), bottom) => {
+                        let under = bottom.flatten().or(back.back);
+                        ("▀".to_string(), Style { front: *top, back: under, ..Style::default() })
+                    }
+                    (None, Some(bottom)) => ("▄".to_string(), Style { front: *bottom, ..back }),
+                }
+            }
+        }
+    }
+}
+
+impl Widget for Canvas {
+    fn get_constraint(&self) -> Constraint {
+        self.constraint.clone()
+    }
+
+    fn get_alignment(&self) -> Alignment {
+        self.alignment
+    }
+
+    fn write_buffer(&mut self, view: &mut BufferView) {
+        let columns = self.columns.min(view.width());
+        let rows = self.rows.min(view.height());
+
+        let mut lines = vec![];
+        let mut effects = vec![];
+        for row in 0..rows {
+            let mut line = String::new();
+            let mut line_effs = vec![];
+            let mut style = None;
+            for column in 0..columns {
+                let (symbol, cell_style) = self.cell(column, row);
+                if style.as_ref() != Some(&cell_style) {
+                    line_effs.extend(cell_style.to_effects(column));
+                    style = Some(cell_style);
+                }
+                line += &symbol;
+            }
+            lines.push(line);
+            effects.push(line_effs);
+        }
+        view.push_rect_buffer(lines, effects, Rect::new(0, 0, columns, rows));
+    }
+}
+
+fn to_isize(value: usize) -> isize {
+    isize::try_from(value).unwrap_or(isize::MAX)
+}
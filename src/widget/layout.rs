@@ -1,6 +1,4 @@
-use std::iter;
-
-use crate::{border::BorderLine, buffer::Rect, effect::Color};
+use crate::{border::BorderLine, buffer::{BufferView, Rect}, effect::Color};
 use super::widget::Widget;
 
 #[derive(Clone, PartialEq, Eq)]
@@ -19,11 +17,11 @@ pub struct Constraint {
 
 impl Constraint {
     pub fn percentage(size: usize, max_length: usize, min_length: usize) -> Self {
-        Constraint { size_type: SizeType::Percentage, size: size, min_length: min_length, max_length: max_length}
+        Constraint { size_type: SizeType::Percentage, size, min_length, max_length }
     }
 
     pub fn constant(size: usize) -> Self {
-        Constraint { size_type: SizeType::Constant, size: size, min_length: size, max_length: size}
+        Constraint { size_type: SizeType::Constant, size, min_length: size, max_length: size }
     }
 }
 
@@ -44,52 +42,54 @@ pub struct Horizontal {
 
 impl Horizontal {
     pub fn new(constraint: Constraint, alignment: Alignment) -> Self {
-        Horizontal { constraint: constraint, alignment: alignment, widgets: vec![], border: None, front_color: None, back_color: None }
+        Horizontal { constraint, alignment, widgets: vec![], border: None, front_color: None, back_color: None }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn add(mut self, widget: Box<dyn Widget>) -> Self {
         self.widgets.push(widget);
-        return self;
+        self
     }
 
     pub fn set_border(mut self, line: Option<BorderLine>) -> Self {
         self.border = line;
-        return self;
+        self
     }
 
     pub fn set_color(mut self, front: Option<Color>, back: Option<Color>) -> Self {
         self.front_color = front;
         self.back_color = back;
-        return self;
+        self
     }
 }
 
 impl Widget for Horizontal {
     fn get_constraint(&self) -> Constraint {
-        return self.constraint.clone();
+        self.constraint.clone()
     }
 
     fn get_alignment(&self) -> Alignment {
-        return self.alignment.clone();
+        self.alignment
     }
 
-    fn write_buffer(&mut self, buffer: &mut crate::buffer::Buffer, rect: Rect) {
+    fn write_buffer(&mut self, view: &mut BufferView) {
+        let rect = view.area();
         let view_buf = rect.make_border_buf(self.border);
-        let veiw_effs = rect.make_color_eff_vec(self.front_color.clone(), self.back_color.clone());
-        buffer.push_rect_buffer(view_buf, veiw_effs, rect);
+        let veiw_effs = rect.make_color_eff_vec(self.front_color, self.back_color);
+        view.push_rect_buffer(view_buf, veiw_effs, rect);
 
         let child_area = rect.make_bordered_rect(self.border);
-        let mut rem_width = child_area.width.clone();
-        let mut child_widths: Vec<usize> = iter::repeat(0).take(self.widgets.len()).into_iter().collect();
+        let mut rem_width = child_area.width;
+        let mut child_widths: Vec<usize> = vec![0; self.widgets.len()];
 
         let mut child_idx_start = vec![];
         let mut child_idx_end = vec![];
 
-        for i in 0..self.widgets.len() {
-            let constraint = self.widgets[i].get_constraint();
+        for (i, widget) in self.widgets.iter().enumerate() {
+            let constraint = widget.get_constraint();
     
             if constraint.size_type == SizeType::Constant {
-                let align = self.widgets[i].get_alignment();
+                let align = widget.get_alignment();
 
                 if rem_width >= constraint.min_length {
                     child_widths[i] = constraint.min_length;
@@ -103,13 +103,13 @@ impl Widget for Horizontal {
                 }
             }
         }
-        let percent_width = rem_width.clone();
-        for i in 0..self.widgets.len() {
-            let constraint = self.widgets[i].get_constraint();
+        let percent_width = rem_width;
+        for (i, widget) in self.widgets.iter().enumerate() {
+            let constraint = widget.get_constraint();
 
             if constraint.size_type == SizeType::Percentage {
                 let width = percent_width * constraint.size / 100;
-                let align = self.widgets[i].get_alignment();
+                let align = widget.get_alignment();
 
                 if rem_width >= width && width >= constraint.max_length {
                     child_widths[i] = constraint.max_length;
@@ -154,22 +154,20 @@ impl Widget for Horizontal {
                 child_widths[i],
                 child_area.height
             );
-            self.widgets[i].write_buffer(buffer, child_rect);
+            self.widgets[i].write_buffer(&mut view.sub_view(child_rect));
             space_start += child_widths[i];
         }
 
         for i in child_idx_end.iter().rev() {
             let child_rect = Rect::new(
-                child_area.x + child_area.width - space_end - child_widths[i.clone()],
+                child_area.x + child_area.width - space_end - child_widths[*i],
                 child_area.y,
-                child_widths[i.clone()],
+                child_widths[*i],
                 child_area.height
             );
-            self.widgets[i.clone()].write_buffer(buffer, child_rect);
-            space_end += child_widths[i.clone()];
+            self.widgets[*i].write_buffer(&mut view.sub_view(child_rect));
+            space_end += child_widths[*i];
         }
-
-
     }
 }
 
@@ -184,52 +182,54 @@ pub struct Vertical {
 
 impl Vertical {
     pub fn new(constraint: Constraint, alignment: Alignment) -> Self {
-        Vertical { constraint: constraint, alignment: alignment, widgets: vec![], border: None, front_color: None, back_color: None }
+        Vertical { constraint, alignment, widgets: vec![], border: None, front_color: None, back_color: None }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn add(mut self, widget: Box<dyn Widget>) -> Self {
         self.widgets.push(widget);
-        return self;
+        self
     }
 
     pub fn set_border(mut self, line: Option<BorderLine>) -> Self {
         self.border = line;
-        return self;
+        self
     }
 
     pub fn set_color(mut self, front: Option<Color>, back: Option<Color>) -> Self {
         self.front_color = front;
         self.back_color = back;
-        return self;
+        self
     }
 }
 
 impl Widget for Vertical {
     fn get_constraint(&self) -> Constraint {
-        return self.constraint.clone();
+        self.constraint.clone()
     }
 
     fn get_alignment(&self) -> Alignment {
-        return self.alignment.clone();
+        self.alignment
     }
 
-    fn write_buffer(&mut self, buffer: &mut crate::buffer::Buffer, rect: Rect) {
+    fn write_buffer(&mut self, view: &mut BufferView) {
+        let rect = view.area();
         let view_buf = rect.make_border_buf(self.border);
-        let veiw_effs = rect.make_color_eff_vec(self.front_color.clone(), self.back_color.clone());
-        buffer.push_rect_buffer(view_buf, veiw_effs, rect);
+        let veiw_effs = rect.make_color_eff_vec(self.front_color, self.back_color);
+        view.push_rect_buffer(view_buf, veiw_effs, rect);
 
         let child_area = rect.make_bordered_rect(self.border);
-        let mut rem_height = child_area.height.clone();
-        let mut child_heights: Vec<usize> = iter::repeat(0).take(self.widgets.len()).into_iter().collect();
+        let mut rem_height = child_area.height;
+        let mut child_heights: Vec<usize> = vec![0; self.widgets.len()];
 
         let mut child_idx_start = vec![];
         let mut child_idx_end = vec![];
 
-        for i in 0..self.widgets.len() {
-            let constraint = self.widgets[i].get_constraint();
+        for (i, widget) in self.widgets.iter().enumerate() {
+            let constraint = widget.get_constraint();
 
             if constraint.size_type == SizeType::Constant {
-                let align = self.widgets[i].get_alignment();
+                let align = widget.get_alignment();
 
                 if rem_height >= constraint.min_length {
                     child_heights[i] = constraint.min_length;
@@ -243,13 +243,13 @@ impl Widget for Vertical {
                 }
             }
         }
-        let percent_height = rem_height.clone();
-        for i in 0..self.widgets.len() {
-            let constraint = self.widgets[i].get_constraint();
+        let percent_height = rem_height;
+        for (i, widget) in self.widgets.iter().enumerate() {
+            let constraint = widget.get_constraint();
 
             if constraint.size_type == SizeType::Percentage {
                 let height = percent_height * constraint.size / 100;
-                let align = self.widgets[i].get_alignment();
+                let align = widget.get_alignment();
 
                 if rem_height >= height && height >= constraint.max_length {
                     child_heights[i] = constraint.max_length;
@@ -294,19 +294,19 @@ impl Widget for Vertical {
                 child_area.width,
                 child_heights[i],
             );
-            self.widgets[i].write_buffer(buffer, child_rect);
+            self.widgets[i].write_buffer(&mut view.sub_view(child_rect));
             space_start += child_heights[i];
         }
 
         for i in child_idx_end.iter().rev() {
             let child_rect = Rect::new(
                 child_area.x,
-                child_area.y + child_area.height - space_end - child_heights[i.clone()],
+                child_area.y + child_area.height - space_end - child_heights[*i],
                 child_area.width,
-                child_heights[i.clone()],
+                child_heights[*i],
             );
-            self.widgets[i.clone()].write_buffer(buffer, child_rect);
-            space_end += child_heights[i.clone()];
+            self.widgets[*i].write_buffer(&mut view.sub_view(child_rect));
+            space_end += child_heights[*i];
         }
     }
 }
\ No newline at end of file
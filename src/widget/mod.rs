@@ -1,3 +1,5 @@
+#[allow(clippy::module_inception)]
 pub mod widget;
 pub mod layout;
+pub mod canvas;
 // pub mod button;
\ No newline at end of file
@@ -1,8 +1,9 @@
-use crate::buffer::{Buffer, Rect};
+use crate::buffer::BufferView;
 use super::layout::{Constraint, Alignment};
 
 pub trait Widget {
     fn get_constraint(&self) -> Constraint;
     fn get_alignment(&self) -> Alignment;
-    fn write_buffer(&mut self, buffer: &mut Buffer, rect: Rect);
+    /// Draws the widget into `view`, whose area is the one laid out for it.
+    fn write_buffer(&mut self, view: &mut BufferView);
 }
\ No newline at end of file